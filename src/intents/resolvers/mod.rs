@@ -126,6 +126,11 @@ impl EntityChild {
     }
 
     pub fn get_reference(&self, prev: u32) -> Reference {
+        // spoken ordinals name a position outright
+        if let Some(reference) = self.value.as_str().and_then(ordinal) {
+            return reference;
+        }
+
         let value = self.get_value();
 
         if prev > 0 {
@@ -151,6 +156,28 @@ impl EntityChild {
     }
 }
 
+// the words counting positions from each end
+const ORDINALS: [&str; 10] = [
+    "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth", "tenth"
+];
+
+/// Map an ordinal phrase such as "the third question" or "second to last" to a reference
+fn ordinal(phrase: &str) -> Option<Reference> {
+    let phrase = phrase.to_lowercase();
+    let words: Vec<&str> = phrase.split_whitespace()
+        .filter(|word| !matches!(*word, "the" | "question" | "section"))
+        .collect();
+
+    match words.as_slice() {
+        ["last"] => Some(Reference::nth_from_end(1)),
+        ["second", "to", "last"] | ["second", "last"] | ["penultimate"] => Some(Reference::nth_from_end(2)),
+        [word] => ORDINALS.iter()
+            .position(|ordinal| ordinal == word)
+            .map(|index| Reference::nth_from_start(index as u32 + 1)),
+        _ => None
+    }
+}
+
 /// Top LU intents
 #[derive(Deserialize, Debug, Clone, Copy)]
 pub enum TopIntents {
@@ -203,4 +230,40 @@ impl LuResponse {
     pub fn top_intent(&self) -> TopIntents {
         self.top_intent
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordinal_words_resolve_to_references(){
+        let cases = vec![
+            ("first", Reference::nth_from_start(1)),
+            ("second", Reference::nth_from_start(2)),
+            ("third question", Reference::nth_from_start(3)),
+            ("fourth", Reference::nth_from_start(4)),
+            ("the fifth", Reference::nth_from_start(5)),
+            ("sixth", Reference::nth_from_start(6)),
+            ("Seventh", Reference::nth_from_start(7)),
+            ("eighth section", Reference::nth_from_start(8)),
+            ("ninth", Reference::nth_from_start(9)),
+            ("tenth", Reference::nth_from_start(10)),
+            ("last", Reference::nth_from_end(1)),
+            ("the last question", Reference::nth_from_end(1)),
+            ("second to last", Reference::nth_from_end(2)),
+        ];
+
+        for (phrase, expected) in cases {
+            let child = EntityChild { value: Value::from(phrase) };
+
+            // ordinals ignore the position of earlier entities
+            assert_eq!(child.get_reference(0), expected, "{}", phrase);
+            assert_eq!(child.get_reference(1), expected, "{}", phrase);
+        }
+
+        assert_eq!(ordinal("eleventh"), None);
+        assert_eq!(EntityChild { value: Value::from("3") }.get_reference(0), Reference::Start(3));
+    }
+}
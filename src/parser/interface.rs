@@ -122,6 +122,48 @@ impl Tag {
         )
     }
 
+    pub fn is_part(&self) -> bool {
+        matches(
+            self.name.get(),
+            "^part$"
+        )
+    }
+
+    pub fn is_option(&self) -> bool {
+        matches(
+            self.name.get(),
+            "^option$"
+        )
+    }
+
+    pub fn is_difficulty(&self) -> bool {
+        matches(
+            self.name.get(),
+            "^difficulty$"
+        )
+    }
+
+    pub fn is_marks(&self) -> bool {
+        matches(
+            self.name.get(),
+            "^marks$"
+        )
+    }
+
+    pub fn is_question_type(&self) -> bool {
+        matches(
+            self.name.get(),
+            "^type$"
+        )
+    }
+
+    pub fn is_answer(&self) -> bool {
+        matches(
+            self.name.get(),
+            "^answer$"
+        )
+    }
+
     pub fn is_item(&self) -> bool {
         matches(
             &self.name.get(),
@@ -144,6 +186,34 @@ impl Tag {
         )
     }
 
+    pub fn is_title(&self) -> bool {
+        matches(
+            self.name.get(),
+            "^title$"
+        )
+    }
+
+    pub fn is_subject(&self) -> bool {
+        matches(
+            self.name.get(),
+            "^subject$"
+        )
+    }
+
+    pub fn is_duration(&self) -> bool {
+        matches(
+            self.name.get(),
+            "^duration$"
+        )
+    }
+
+    pub fn is_time_limit(&self) -> bool {
+        matches(
+            self.name.get(),
+            "^time_limit$"
+        )
+    }
+
     // new page
     pub fn is_page(&self) -> bool {
         matches(
@@ -1,12 +1,14 @@
-pub use crate::{TagToken, Tag, parser::interface::TagName};
+pub use crate::{TagToken, Tag, parser::interface::{TagName, TagKind}};
 use std::borrow::Cow::{self, Borrowed};
 use super::{interface, QuestionPaper};
 mod section_builder;
+mod schema;
 use std::mem::replace;
 
-use section_builder::SectionBuilder;
+use section_builder::{SectionBuilder, Section};
+pub use schema::{PaperSchema, SectionSchema, QuestionSchema};
 
-pub use interface::{NodeData, Node, Builder, SectionData, QuestionData};
+pub use interface::{NodeData, Node, NodeId, Builder, SectionData, SubPartData, QuestionData, QuestionType, Difficulty, Metadata};
 
 
 /// Builder modes controll how the builder interprets an input tag
@@ -23,7 +25,10 @@ pub struct QPaperBuilder {
     mode: Option<Modes>,
     instructions: Vec<String>,
     section_builder: SectionBuilder,
-    total_questions: u32
+    total_questions: u32,
+    metadata: Metadata,
+    shuffle_seed: Option<u64>,
+    inline_sections: bool
 }
 
 
@@ -35,10 +40,23 @@ impl QPaperBuilder {
             mode: None,
             instructions: vec![],
             section_builder: SectionBuilder::new(),
-            total_questions: 0
+            total_questions: 0,
+            metadata: Metadata::default(),
+            shuffle_seed: None,
+            inline_sections: true
         }
     }
 
+    /// Shuffle each question's options, reproducibly for the same seed
+    pub fn shuffle_options(&mut self, seed: u64) {
+        self.shuffle_seed = Some(seed);
+    }
+
+    /// Keep section headers as navigable nodes, or drop them and record the section on each question instead
+    pub fn inline_sections(&mut self, inline: bool) {
+        self.inline_sections = inline;
+    }
+
 
     /// Process a tag in the current mode
     fn process_in_mode(&mut self, tag: Tag){
@@ -50,6 +68,8 @@ impl QPaperBuilder {
                         if tag.is_end_tag(){
                             self.process_instruction_text(tag);
                         }
+                    }else if tag.is_end_tag(){
+                        self.process_metadata(tag);
                     }
     
                 },
@@ -91,7 +111,7 @@ impl QPaperBuilder {
         let is_self_closing = match tag {
             Tag {
                 is_self_closing: true,
-                kind: StartTag,
+                kind: TagKind::StartTag,
                 ..
             } => true,
             _ => false
@@ -124,6 +144,7 @@ impl QPaperBuilder {
 
         // insert a navigator node
         self.nodes.push(Node {
+            id: NodeId(index),
             data,
             index,
             parent,
@@ -166,11 +187,10 @@ impl Builder for QPaperBuilder {
     }
 
     fn end(&mut self) -> Self::Item {
-        let total = self.nodes.len();
         let nodes = replace(&mut self.nodes, vec![]);
 
-
-        QuestionPaper::new(nodes, total -1, self.total_questions)
+        QuestionPaper::new(nodes, self.total_questions)
+            .with_metadata(self.metadata.clone())
     }
 }
 
@@ -186,22 +206,102 @@ impl QPaperBuilder {
         }      
     }
 
+    // fill in the paper title, subject and duration
+    fn process_metadata(&mut self, tag: Tag){
+        let value = match tag.value() {
+            Some(value) => value.trim().to_string(),
+            None => return
+        };
+
+        if tag.is_title(){
+            self.metadata.title = Some(value);
+        }else if tag.is_subject(){
+            self.metadata.subject = Some(value);
+        }else if tag.is_duration(){
+            match value.parse() {
+                Ok(minutes) => self.metadata.duration_minutes = Some(minutes),
+                Err(_) => self.emit_error(Borrowed("Paper duration must be a whole number of minutes"))
+            }
+        }else if tag.is_time_limit(){
+            match value.parse() {
+                Ok(minutes) => self.metadata.time_limit_minutes = Some(minutes),
+                Err(_) => self.emit_error(Borrowed("Time limit must be a whole number of minutes"))
+            }
+        }
+    }
+
     // insert a section
     fn insert_section(&mut self) {
         let section = self.section_builder.end();
 
+        self.append_section(section);
 
+        self.section_builder.clear();
+    }
+
+    // append a finished section and its questions
+    fn append_section(&mut self, section: Section) {
         self.total_questions = section.total_questions;
 
-        // append the section and its children
-        let mut prev = None;
+        // append the section and its children, or hang the questions off the document without a header
+        let (parent, mut prev, name) = if self.inline_sections {
+            (self.append(NodeData::Section(section.section), Some(0), None), None, None)
+        }else{
+            (0, self.nodes[0].last_child, Some(section.section.section_name))
+        };
+
+        for (mut question, parts) in section.questions.into_iter().zip(section.parts){
+            if question.question_type.is_none(){
+                question.question_type = Some(QuestionType::infer(&question));
+            }
+
+            if let Some(seed) = self.shuffle_seed {
+                shuffle(&mut question, seed);
+            }
 
-        let parent = self.append(NodeData::Section(section.section), Some(0), None);
+            question.section = name.clone();
 
-        for question in section.questions{
-            prev = Some(self.append(NodeData::Question(question), Some(parent), prev));
+            let index = self.append(NodeData::Question(question), Some(parent), prev);
+            prev = Some(index);
+
+            // parts are children of their question, after it in document order
+            let mut prev_part = None;
+            for part in parts {
+                prev_part = Some(self.append(NodeData::SubPart(part), Some(index), prev_part));
+            }
         }
+    }
+}
 
-        self.section_builder.clear();
+// shuffle the options of a question, seeded per question so each is shuffled differently
+fn shuffle(question: &mut QuestionData, seed: u64) {
+    let order = shuffled_order(question.options.len(), question.question_number, seed);
+
+    question.options = order.iter().map(|original| question.options[*original].clone()).collect();
+    question.original_order = order;
+}
+
+/// A reproducible permutation of `len` option positions for a question and seed
+pub fn shuffled_order(len: usize, question_number: u32, seed: u64) -> Vec<usize> {
+    let mut state = seed ^ u64::from(question_number).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let mut order: Vec<usize> = (0..len).collect();
+
+    // fisher yates
+    for i in (1..order.len()).rev() {
+        let j = (splitmix(&mut state) % (i as u64 + 1)) as usize;
+        order.swap(i, j);
     }
-}
\ No newline at end of file
+
+    order
+}
+
+// splitmix64, a small deterministic generator
+fn splitmix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+    z ^ (z >> 31)
+}
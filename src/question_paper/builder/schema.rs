@@ -0,0 +1,92 @@
+use super::{QPaperBuilder, Builder, QuestionPaper, NodeData, SectionData, QuestionData, SubPartData, QuestionType, Difficulty, Metadata};
+use super::section_builder::Section;
+use serde::Deserialize;
+
+/// A whole paper as plain data, for callers that already hold it deserialized
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct PaperSchema {
+    pub title: Option<String>,
+    pub subject: Option<String>,
+    pub duration_minutes: Option<u32>,
+    pub time_limit_minutes: Option<u32>,
+    #[serde(default)]
+    pub sections: Vec<SectionSchema>
+}
+
+/// A named section and its questions
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct SectionSchema {
+    pub name: String,
+    #[serde(default)]
+    pub questions: Vec<QuestionSchema>
+}
+
+/// A question as the markup would describe it, numbering is left to the builder
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct QuestionSchema {
+    pub question: String,
+    #[serde(default)]
+    pub options: Vec<String>,
+    pub answer: Option<String>,
+    pub marks: Option<u32>,
+    /// The same values a `<type>` tag accepts, inferred when missing or unknown
+    #[serde(alias = "type")]
+    pub question_type: Option<String>,
+    pub difficulty: Option<String>,
+    #[serde(default)]
+    pub parts: Vec<String>
+}
+
+impl QPaperBuilder {
+    /// Build a paper straight from a schema, without going through markup.
+    ///
+    /// Sections go through the same assembly as parsed ones, so shuffled options and stripped section headers apply.
+    pub fn build_schema(&mut self, schema: PaperSchema) -> QuestionPaper {
+        self.append(NodeData::Document, None, None);
+        self.metadata = Metadata {
+            title: schema.title,
+            subject: schema.subject,
+            duration_minutes: schema.duration_minutes,
+            time_limit_minutes: schema.time_limit_minutes
+        };
+
+        // questions are numbered across the whole paper, as they are in markup
+        let mut number = self.total_questions;
+
+        for section in schema.sections {
+            let num_of_questions = section.questions.len() as u32;
+            let mut questions = vec![];
+            let mut parts = vec![];
+
+            for schema in section.questions {
+                number += 1;
+
+                let question = QuestionData {
+                    question: schema.question,
+                    question_number: number,
+                    options: schema.options,
+                    key: schema.answer,
+                    marks: schema.marks,
+                    question_type: schema.question_type.as_deref().and_then(QuestionType::parse),
+                    difficulty: schema.difficulty.as_deref().and_then(Difficulty::parse),
+                    ..QuestionData::default()
+                };
+
+                parts.push(schema.parts.into_iter()
+                    .enumerate()
+                    .map(|(position, text)| SubPartData::new(position, text, number))
+                    .collect());
+                questions.push(question);
+            }
+
+            self.append_section(Section {
+                section: SectionData::new(section.name, num_of_questions),
+                questions,
+                parts,
+                total_questions: number
+            });
+        }
+
+        self.end()
+    }
+}
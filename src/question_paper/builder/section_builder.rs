@@ -1,5 +1,5 @@
-use super::{Builder, Tag, TagName, QuestionData, SectionData};
-use std::mem::replace;
+use super::{Builder, Tag, TagName, QuestionData, QuestionType, Difficulty, SectionData, SubPartData};
+use std::mem::take;
 /// builder modes for a setion
 #[derive(Debug)]
 enum Modes {
@@ -10,6 +10,8 @@ enum Modes {
 pub struct Section {
     pub section: SectionData,
     pub questions: Vec<QuestionData>,
+    /// The sub-parts of each question, in the same order as `questions`
+    pub parts: Vec<Vec<SubPartData>>,
     pub total_questions: u32
 }
 
@@ -22,6 +24,7 @@ pub struct SectionBuilder {
     current_page: u32,
     last_page_name: Option<TagName>,
     questions: Vec<QuestionData>,
+    parts: Vec<Vec<SubPartData>>,
     current_section_name: String
 }
 
@@ -34,6 +37,7 @@ impl SectionBuilder {
             current_page: 1,
             last_page_name: None,
             questions: vec![],
+            parts: vec![],
             current_section_name: String::new()
         }
     }
@@ -42,6 +46,7 @@ impl SectionBuilder {
        self.mode = None;
        self.num_of_questions = 0; 
        self.questions.clear();
+       self.parts.clear();
     }
 
 
@@ -59,22 +64,43 @@ impl SectionBuilder {
                 };
 
                 self.questions.push(question_data);
+                self.parts.push(vec![]);
                 self.current_question += 1;
                 self.num_of_questions += 1;
+            }else if tag.is_option(){
+                // options belong to the question read before them
+                if let (Some(question), Some(option)) = (self.questions.last_mut(), tag.value()) {
+                    question.options.push(option);
+                }
+            }else if tag.is_part(){
+                // parts are lettered in order and do not count as questions
+                if let (Some(question), Some(parts), Some(text)) = (self.questions.last(), self.parts.last_mut(), tag.value()) {
+                    parts.push(SubPartData::new(parts.len(), text, question.question_number));
+                }
+            }else if tag.is_answer(){
+                if let Some(question) = self.questions.last_mut() {
+                    question.key = tag.value();
+                }
+            }else if tag.is_difficulty(){
+                if let (Some(question), Some(value)) = (self.questions.last_mut(), tag.value()) {
+                    question.difficulty = Difficulty::parse(&value);
+                }
+            }else if tag.is_marks(){
+                if let (Some(question), Some(value)) = (self.questions.last_mut(), tag.value()) {
+                    question.marks = value.trim().parse().ok();
+                }
+            }else if tag.is_question_type(){
+                // explicit markup wins over inference, unknown types are left to be inferred
+                if let (Some(question), Some(value)) = (self.questions.last_mut(), tag.value()) {
+                    question.question_type = QuestionType::parse(&value);
+                }
             }
         }
     }
 
     // insert a section meta tag
     fn get_section_data(&self) -> SectionData {
-        SectionData {
-            section_name: self.current_section_name.clone(),
-            num_of_questions: self.num_of_questions,
-            num_of_marked: 0,
-            num_of_skipped: 0,
-            num_of_attempted: 0,
-            num_of_remaining: 0,
-        }
+        SectionData::new(self.current_section_name.clone(), self.num_of_questions)
     }
 }
 
@@ -134,8 +160,10 @@ impl Builder for SectionBuilder {
     fn end(&mut self) -> Self::Item {
         Section {
             section: self.get_section_data(),
-            questions: replace(&mut self.questions, vec![]),
-            total_questions: self.current_question
+            questions: take(&mut self.questions),
+            parts: take(&mut self.parts),
+            // the question counter runs one ahead of the questions seen so far
+            total_questions: self.current_question - 1
         }
     }
 }
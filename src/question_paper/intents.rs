@@ -1,36 +1,129 @@
-use super::{Node, Predicate, NodeData, Find};
+use super::{Node, Predicate, NodeData, Find, FullQuestion, NodeStatus, QuestionType};
 use std::borrow::Cow;
+use std::fmt;
 use serde::Serialize;
 
 // intents interface
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Read {
     Question(Reference),
     Section(Reference),
+    FullQuestion(Reference),
+    QuestionContaining(String),
+    /// Every question with a printed number, for papers that restart numbering per section
+    NumberedQuestions(u32),
+    /// Every question from a reference to the end of the paper, for continuous playback
+    ReadFrom(Reference),
+    /// A question of the given type, counted from the reference like `Question`
+    QuestionOfType(QuestionType, Reference),
+    /// A question with its options read in a seeded shuffled order, answered by the letters read out
+    ShuffledQuestion(Reference, u64),
+    /// The question where the running total of marks reaches a percentage of the paper's marks
+    MarksThreshold(u32),
+    /// A question together with the answer recorded for it
+    QuestionWithAnswer(Reference),
+    /// Any node, headers and parts included, for stepping through a paper node by node
+    Any(Reference),
+}
+
+impl Read {
+    /// The same read anchored on a node index instead of the cursor, so repeating it reads the same node again
+    pub fn pinned(&self, index: usize) -> Read {
+        let pin = |reference: &Reference| match reference {
+            Reference::Current(_) => Reference::Absolute(index),
+            other => *other
+        };
+
+        match self {
+            Read::Question(reference) => Read::Question(pin(reference)),
+            Read::Section(reference) => Read::Section(pin(reference)),
+            Read::FullQuestion(reference) => Read::FullQuestion(pin(reference)),
+            Read::QuestionContaining(_) => Read::Question(Reference::Absolute(index)),
+            Read::QuestionOfType(question_type, reference) => Read::QuestionOfType(*question_type, pin(reference)),
+            Read::ShuffledQuestion(reference, seed) => Read::ShuffledQuestion(pin(reference), *seed),
+            Read::QuestionWithAnswer(reference) => Read::QuestionWithAnswer(pin(reference)),
+            Read::Any(reference) => Read::Any(pin(reference)),
+            // the cursor ends on the last node played, not the first
+            other => other.clone()
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Write {
     Mark(Vec<Read>),
+    MarkAs(Vec<Read>, MarkReason),
+    MarkNumber(u32),
     Skip(Vec<Read>),
-    Note(Vec<Read>, String)
+    Note(Vec<Read>, String),
+    NoteRange(u32, u32, String),
+    Answer(Vec<Read>, String),
+    /// A free text answer, for questions without options
+    AnswerText(Vec<Read>, String),
+    GlobalNote(String),
+    /// Skip a question and record why in a note
+    SkipWithNote(Vec<Read>, String)
+}
+
+
+/// Why a question was marked for review
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash, Default, Serialize)]
+pub enum MarkReason {
+    #[default]
+    Review,
+    Revisit,
+    Unsure,
+    Guess
 }
 
+impl MarkReason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Review => "for review",
+            Self::Revisit => "to revisit",
+            Self::Unsure => "as unsure",
+            Self::Guess => "as a guess"
+        }
+    }
+}
 
 /// Reference with their skip values
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Reference {
     Start(i32),
     End(i32),
-    Current(i32)
+    Current(i32),
+    /// A node by its position in the paper
+    Absolute(usize)
 }
 
 impl Reference {
+    /// The next match after the current position
+    pub fn next() -> Self {
+        Self::Current(1)
+    }
+
+    /// The match before the current position
+    pub fn previous() -> Self {
+        Self::Current(-1)
+    }
+
+    /// The nth match counting from the start of the paper, starting at 1
+    pub fn nth_from_start(n: u32) -> Self {
+        Self::Start(n as i32)
+    }
+
+    /// The nth match counting back from the end of the paper, where 1 is the last
+    pub fn nth_from_end(n: u32) -> Self {
+        Self::End(-(n as i32))
+    }
+
     pub fn is_forward(&self) -> bool {
         let val = match self {
             Self::Start(c) => c,
             Self::Current(c) => c,
-            Self::End(c) => c
+            Self::End(c) => c,
+            Self::Absolute(_) => return true
         };
 
         val >= &0 
@@ -41,28 +134,148 @@ impl Reference {
 pub enum Intent {
     ReadIntent(Read),
     WriteIntent(Write),
-    Meta(MetaIntent)
+    Meta(MetaIntent),
+    /// A phrase that could not be classified, kept so it can be echoed back
+    Unknown(String)
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum MetaIntent {
     Skipped,
-    Marked
+    Marked,
+    Notes(NoteOrder),
+    /// The paper title, subject and duration
+    Info,
+    /// Minutes left in a timed exam, given the minutes elapsed so far
+    TimeRemaining(u32),
+    /// The questions the most time has been spent on
+    SlowestQuestions,
+    /// The marks the answered questions are worth out of the paper's total
+    MarkBudget,
+    /// Roughly how many words the questions run to
+    Length
+}
+
+/// The order in which notes are read back
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum NoteOrder {
+    /// In the order they were taken
+    #[default]
+    Chronological,
+    /// Most recent first
+    RecentFirst
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize)]
 pub enum WriteResult {
     Success(String),
+    /// The write succeeded and the cursor moved on to the node given, ready to be read
+    Advanced(String, NodeData),
     Error(Cow<'static, str>)
 }
 
-pub type ReadResult = Result<Node, Cow<'static, str>>;
+pub type ReadResult = Result<Node, ReadError>;
+
+/// Why a read failed, with the index of the nearest matching node when there is one to suggest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReadError {
+    pub message: Cow<'static, str>,
+    pub hint: Option<usize>
+}
+
+impl ReadError {
+    /// Suggest a node the caller may have meant
+    pub fn with_hint(mut self, hint: Option<usize>) -> Self {
+        self.hint = hint;
+        self
+    }
+}
+
+impl From<Cow<'static, str>> for ReadError {
+    fn from(message: Cow<'static, str>) -> Self {
+        ReadError { message, hint: None }
+    }
+}
+
+impl From<&'static str> for ReadError {
+    fn from(message: &'static str) -> Self {
+        ReadError { message: Cow::Borrowed(message), hint: None }
+    }
+}
+
+impl From<String> for ReadError {
+    fn from(message: String) -> Self {
+        ReadError { message: Cow::Owned(message), hint: None }
+    }
+}
+
+impl PartialEq<&str> for ReadError {
+    fn eq(&self, other: &&str) -> bool {
+        self.message == *other
+    }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// One of several questions a reference could mean, with the section it is in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Candidate {
+    pub index: usize,
+    pub section: Option<String>,
+    pub data: NodeData
+}
+
+/// A question read back with the answer recorded for it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AnsweredQuestion {
+    pub data: NodeData,
+    pub answer: Option<String>,
+    pub answered: bool
+}
+
+/// Structured data behind a meta response, alongside the sentence read out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MetaPayload {
+    pub message: String,
+    pub count: usize,
+    pub indices: Vec<usize>
+}
+
+/// A node read by a read intent, with what the reader is told alongside it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReadOutcome<D = NodeData> {
+    pub data: D,
+    /// The node's flags at the time it was read
+    pub status: NodeStatus,
+    /// How many questions the cursor moved to reach the node, negative going back
+    pub distance: isize,
+    /// The section entered on the way, when sections are announced
+    pub section: Option<String>
+}
 
 #[derive(Debug, Clone, Serialize)]
 pub enum IntentResult {
-    Read(Result<NodeData, Cow<'static, str>>),
+    Read(Result<ReadOutcome, ReadError>),
+    FullQuestion(Result<FullQuestion, ReadError>),
+    Candidates(Result<Vec<Candidate>, Cow<'static, str>>),
+    Playback(Result<Vec<NodeData>, ReadError>),
+    Answered(Result<AnsweredQuestion, ReadError>),
     Write(WriteResult),
-    Meta(String)
+    Meta(String),
+    MetaData(MetaPayload)
+}
+
+/// An intent result that borrows the content of the node read from the paper
+///
+/// Only question and section reads borrow, every other intent gives its owned result.
+#[derive(Debug)]
+pub enum IntentResultRef<'a> {
+    Read(Result<ReadOutcome<&'a NodeData>, ReadError>),
+    Owned(IntentResult)
 }
 
 /// Types that can be used to resolve read intents
@@ -75,6 +288,12 @@ pub trait Reader {
     /// Resolve a section
     fn resolve_section(&mut self, reference: &Reference) -> ReadResult;
 
+    /// Resolve the next question containing a keyword
+    fn resolve_containing(&mut self, term: &str) -> ReadResult;
+
+    /// Resolve the question where the running marks reach a percentage of the total
+    fn resolve_marks_threshold(&mut self, percent: u32) -> ReadResult;
+
     /// Resolve from a reference
     fn resolve_referece<P: Predicate>(&mut self, reference: &Reference, predicate: P) -> ReadResult;
 
@@ -94,12 +313,33 @@ pub trait Writer {
     fn resolve_write_intent(&mut self, write_intent: &Write) ->  WriteResult;
 
     // process a read intent and mark it for review
-    fn mark_for_review(&mut self, read_intent: &Vec<Read>) -> WriteResult;
+    fn mark_for_review(&mut self, read_intent: &[Read]) -> WriteResult;
+
+    /// mark the component found with a reason
+    fn mark_as(&mut self, read_intent: &[Read], reason: MarkReason) -> WriteResult;
+
+    /// Mark a question by its printed number
+    fn mark_number(&mut self, number: u32) -> WriteResult;
 
     /// Skip the component found on the read intent position
-    fn skip(&mut self, read_intent: &Vec<Read>) -> WriteResult;
+    fn skip(&mut self, read_intent: &[Read]) -> WriteResult;
 
     /// take a note on the component found
-    fn note(&mut self, read_intent: &Vec<Read>, note: String) -> WriteResult;
+    fn note(&mut self, read_intent: &[Read], note: String) -> WriteResult;
+
+    /// take the same note on every question numbered within a range
+    fn note_range(&mut self, from: u32, to: u32, note: String) -> WriteResult;
+
+    /// record an answer for the component found
+    fn answer(&mut self, read_intent: &[Read], answer: String) -> WriteResult;
+
+    /// record a free text answer for a question without options
+    fn answer_text(&mut self, read_intent: &[Read], answer: String) -> WriteResult;
+
+    /// take a note on the paper as a whole
+    fn global_note(&mut self, note: String) -> WriteResult;
+
+    /// skip the component found and attach a note to it, or do neither
+    fn skip_with_note(&mut self, read_intent: &[Read], note: String) -> WriteResult;
 
 }
\ No newline at end of file
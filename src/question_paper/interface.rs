@@ -1,14 +1,50 @@
 use crate::Tag;
-use super::QuestionPaper;
+use super::{QuestionPaper, MarkReason};
 use std::borrow::Cow::{self, Borrowed};
-use serde::Serialize;
+use serde::{Serialize, Deserialize};
+use regex::Regex;
+use std::sync::OnceLock;
 
 
+// abbreviations spelled out for speech
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("e.g.", "for example"),
+    ("i.e.", "that is"),
+    ("etc.", "et cetera"),
+    ("approx.", "approximately"),
+    ("Fig.", "Figure"),
+];
+
+// punctuation that may open or close a word without changing it
+const LEADING: &[char] = &['(', '"', '\''];
+const TRAILING: &[char] = &[',', ';', ':', '!', '?', ')', '"', '\''];
+
+// markdown heading markers at the start of a line
+const HEADING: &str = r"(?m)^[ \t]*#+[ \t]*";
+
+// paired markdown emphasis, the markers are dropped and the text between them kept
+const EMPHASIS: &[&str] = &[
+    r"(^|[^\w*])\*\*([^\s*](?:[^*]*[^\s*])?)\*\*($|[^\w*])",
+    r"(^|[^\w_])__([^\s_](?:[^_]*[^\s_])?)__($|[^\w_])",
+    r"(^|[^\w*])\*([^\s*](?:[^*]*[^\s*])?)\*($|[^\w*])",
+    r"(^|[^\w_])_([^\s_](?:[^_]*[^\s_])?)_($|[^\w_])",
+    r"()`([^`]+)`()",
+];
+
+// math placeholders and how they are announced
+const MATH: &[(&str, &str)] = &[
+    ("[math]", "math expression"),
+    ("[equation]", "equation"),
+    ("[diagram]", "diagram"),
+];
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub enum NodeData {
     Document,
     Section(SectionData),
     Question(QuestionData),
+    /// A lettered part of the question it hangs off
+    SubPart(SubPartData),
     Instruction(String)
 }
 
@@ -27,6 +63,132 @@ impl NodeData {
         }
     }
 
+    pub fn is_sub_part(&self) -> bool {
+        matches!(self, Self::SubPart(_))
+    }
+
+    /// The name of a section node
+    pub fn section_name(&self) -> Option<&str> {
+        match self {
+            Self::Section(ref section) => Some(&section.section_name),
+            _ => None
+        }
+    }
+
+    /// The printed number of a question node
+    pub fn question_number(&self) -> Option<u32> {
+        match self {
+            Self::Question(ref question) => Some(question.question_number),
+            _ => None
+        }
+    }
+
+    /// How many words the node's text holds, a question's options included
+    pub fn word_count(&self) -> usize {
+        let count = |text: &str| text.split_whitespace().count();
+
+        match self {
+            Self::Document => 0,
+            Self::Section(ref section) => count(&section.section_name),
+            Self::Question(ref question) => {
+                count(&question.question) + question.options.iter().map(|option| count(option)).sum::<usize>()
+            },
+            Self::SubPart(ref part) => count(&part.text),
+            Self::Instruction(ref instruction) => count(instruction)
+        }
+    }
+
+    /// The node's content normalized for text to speech
+    pub fn accessible_text(&self) -> String {
+        match self {
+            Self::Document => String::new(),
+            Self::Section(ref section) => speakable(&section.section_name),
+            Self::Question(ref question) => format!("Question {}. {}", question.question_number, speakable(&question.question)),
+            Self::SubPart(ref part) => format!("Part {}. {}", part.label, speakable(&part.text)),
+            Self::Instruction(ref instruction) => speakable(instruction)
+        }
+    }
+
+}
+
+// the heading and emphasis patterns, compiled on first use
+fn markdown() -> &'static (Regex, Vec<Regex>) {
+    static MARKDOWN: OnceLock<(Regex, Vec<Regex>)> = OnceLock::new();
+
+    MARKDOWN.get_or_init(|| {
+        let emphasis = EMPHASIS.iter().map(|pattern| Regex::new(pattern).unwrap()).collect();
+
+        (Regex::new(HEADING).unwrap(), emphasis)
+    })
+}
+
+// apply the speech tables to a piece of text
+fn speakable(text: &str) -> String {
+    let mut text = text.to_string();
+
+    for (placeholder, label) in MATH {
+        text = text.replace(placeholder, label);
+    }
+
+    let (heading, emphasis) = markdown();
+    text = heading.replace_all(&text, "").into_owned();
+
+    for re in emphasis {
+        // neighbouring spans share a boundary, so replace until nothing is left to strip
+        loop {
+            let stripped = re.replace_all(&text, "${1}${2}${3}").into_owned();
+
+            if stripped == text {
+                break;
+            }
+
+            text = stripped;
+        }
+    }
+
+    let words: Vec<&str> = text.split_whitespace().collect();
+
+    words.iter()
+        .enumerate()
+        .map(|(position, word)| {
+            let opened = word.trim_start_matches(LEADING);
+            let leading = &word[..word.len() - opened.len()];
+            let core = opened.trim_end_matches(TRAILING);
+            let trailing = &opened[core.len()..];
+
+            // "No." is only a number when one follows it
+            let before_digit = words.get(position + 1)
+                .and_then(|next| next.chars().next())
+                .is_some_and(|c| c.is_ascii_digit());
+
+            let spoken = if core == "No." && before_digit {
+                Some("Number".to_string())
+            }else{
+                ABBREVIATIONS.iter()
+                    .find(|(short, _)| *short == core)
+                    .map(|(_, long)| long.to_string())
+                    .or_else(|| fraction(core))
+            };
+
+            match spoken {
+                Some(spoken) => format!("{}{}{}", leading, spoken, trailing),
+                None => word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// read a numeric fraction like 3/4 as "3 over 4"
+fn fraction(word: &str) -> Option<String> {
+    let (numerator, denominator) = word.split_once('/')?;
+    let is_number = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+
+    if is_number(numerator) && is_number(denominator) {
+        Some(format!("{} over {}", numerator, denominator))
+    }else{
+        None
+    }
 }
 
 // Section data
@@ -46,7 +208,140 @@ pub struct QuestionData{
     pub question: String,
     pub question_number: u32,
     pub page_number: u32,
-    pub marked: bool
+    pub marked: bool,
+    pub options: Vec<String>,
+    /// The original position of each option, empty when the options are in their original order
+    pub original_order: Vec<usize>,
+    /// The answer key, an option letter in the original order for multiple choice questions
+    pub key: Option<String>,
+    /// The section the question is in, set only when section headers are not kept as nodes
+    pub section: Option<String>,
+    /// The kind of answer expected, from explicit markup or inferred by the builder
+    pub question_type: Option<QuestionType>,
+    /// How many marks the question is worth, when the paper says
+    pub marks: Option<u32>,
+    /// How hard the question is, when the paper says
+    pub difficulty: Option<Difficulty>
+}
+
+/// One part of a multi-part question, such as (b)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SubPartData {
+    /// The part's letter, a for the first part
+    pub label: String,
+    pub text: String,
+    /// The printed number of the question the part belongs to
+    pub question_number: u32
+}
+
+impl SectionData {
+    /// A section with nothing attempted, skipped or marked yet
+    pub fn new(section_name: String, num_of_questions: u32) -> Self {
+        SectionData {
+            num_of_questions,
+            num_of_attempted: 0,
+            num_of_skipped: 0,
+            num_of_marked: 0,
+            num_of_remaining: 0,
+            section_name
+        }
+    }
+}
+
+impl SubPartData {
+    /// The part at a zero based position within its question, lettered from a, with aa following z
+    pub fn new(position: usize, text: String, question_number: u32) -> Self {
+        SubPartData {
+            label: part_label(position),
+            text,
+            question_number
+        }
+    }
+}
+
+// letter a zero based position like spreadsheet columns: a to z, then aa, ab and on
+fn part_label(position: usize) -> String {
+    let mut letters = vec![];
+    let mut remaining = position + 1;
+
+    while remaining > 0 {
+        remaining -= 1;
+        letters.push((b'a' + (remaining % 26) as u8) as char);
+        remaining /= 26;
+    }
+
+    letters.iter().rev().collect()
+}
+
+/// How hard a question is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard
+}
+
+impl Difficulty {
+    /// Parse the value of a `<difficulty>` tag
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "easy" => Some(Difficulty::Easy),
+            "medium" => Some(Difficulty::Medium),
+            "hard" => Some(Difficulty::Hard),
+            _ => None
+        }
+    }
+}
+
+/// The kind of answer a question expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum QuestionType {
+    MultipleChoice,
+    ShortAnswer,
+    Essay,
+    TrueFalse
+}
+
+// opening words that ask for an extended answer
+const ESSAY_CUES: &[&str] = &["explain", "describe", "discuss", "compare", "evaluate", "analyse", "analyze"];
+
+// questions longer than this many words are taken to be essays
+const ESSAY_WORDS: usize = 25;
+
+impl QuestionType {
+    /// Parse the value of a `<type>` tag
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace(['-', ' '], "_").as_str() {
+            "multiple_choice" | "mcq" => Some(QuestionType::MultipleChoice),
+            "short_answer" | "short" => Some(QuestionType::ShortAnswer),
+            "essay" | "long_answer" => Some(QuestionType::Essay),
+            "true_false" => Some(QuestionType::TrueFalse),
+            _ => None
+        }
+    }
+
+    /// Guess the type from the question's options and wording
+    pub fn infer(question: &QuestionData) -> Self {
+        let is_true_false = question.options.len() == 2 && question.options.iter().all(|option| {
+            option.trim().eq_ignore_ascii_case("true") || option.trim().eq_ignore_ascii_case("false")
+        });
+
+        if is_true_false {
+            return QuestionType::TrueFalse;
+        }
+
+        if !question.options.is_empty(){
+            return QuestionType::MultipleChoice;
+        }
+
+        let first = question.question.split_whitespace().next().unwrap_or_default().to_lowercase();
+
+        if ESSAY_CUES.contains(&first.as_str()) || question.question.split_whitespace().count() > ESSAY_WORDS {
+            QuestionType::Essay
+        }else{
+            QuestionType::ShortAnswer
+        }
+    }
 }
 
 impl Default for QuestionData {
@@ -55,22 +350,124 @@ impl Default for QuestionData {
             question: String::new(),
             question_number: 0,
             page_number: 1,
-            marked: false
+            marked: false,
+            options: vec![],
+            original_order: vec![],
+            key: None,
+            section: None,
+            question_type: None,
+            marks: None,
+            difficulty: None
+        }
+    }
+}
+
+impl QuestionData {
+    /// The position an option had before the options were shuffled
+    pub fn original_position(&self, position: usize) -> usize {
+        self.original_order.get(position).cloned().unwrap_or(position)
+    }
+
+    /// Check an answer against the key, `None` when there is no key
+    pub fn grade(&self, answer: &str) -> Option<bool> {
+        let key = self.key.as_ref()?;
+
+        if self.options.is_empty(){
+            return Some(normalize(key) == normalize(answer));
         }
+
+        // map the key letter to its original option, then find where that option is shown now
+        let letter = key.trim().chars().next()?.to_ascii_uppercase();
+        let original = (letter as u8).checked_sub(b'A')? as usize;
+
+        let correct = (0..self.options.len())
+            .find(|position| self.original_position(*position) == original)
+            .map(|position| &self.options[position]);
+
+        Some(correct.is_some_and(|option| option == answer))
     }
 }
 
+// fold case, whitespace and trailing punctuation so free text answers compare fairly
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(|c: char| c.is_ascii_punctuation())
+        .to_lowercase()
+}
+
+/// A question stem read together with its answer options
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FullQuestion {
+    pub question_number: u32,
+    pub stem: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<String>>
+}
+
+impl From<&QuestionData> for FullQuestion {
+    fn from(question: &QuestionData) -> Self {
+        let options = if question.options.is_empty(){
+            None
+        }else{
+            Some(question.options.clone())
+        };
+
+        FullQuestion {
+            question_number: question.question_number,
+            stem: question.question.clone(),
+            options
+        }
+    }
+}
+
+
+/// Descriptive information about a paper, any of which may be missing
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub subject: Option<String>,
+    pub duration_minutes: Option<u32>,
+    pub time_limit_minutes: Option<u32>
+}
+
+/// A stable node identity, assigned at build time and independent of the node's position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NodeId(pub usize);
+
+impl NodeId {
+    /// The id general notes are filed under, no node is ever given it
+    pub const GLOBAL: NodeId = NodeId(usize::MAX);
+}
+
+/// The flags a node carries at the time it is read
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct NodeStatus {
+    pub marked: bool,
+    pub skipped: bool,
+    pub answered: bool,
+    pub has_note: bool
+}
+
+/// A marked node and the reason it was marked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mark {
+    pub data: NodeData,
+    pub reason: MarkReason
+}
 
 /// A note can be taken on any node
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Note{
-    pub index: usize,
+    pub id: NodeId,
     pub note: String
 }
 
 /// A single document node
 #[derive(Debug, Clone)]
 pub struct Node {
+    pub id: NodeId,
     pub data: NodeData,
     pub index: usize,
     pub parent: Option<usize>,
@@ -95,6 +492,36 @@ impl<'a> NodeIndex<'a> {
         }
     }
 
+    /// The node behind this index, with its tree links for custom navigation
+    ///
+    /// ```
+    /// use interactive_paper::{QPaperBuilder, Builder};
+    /// use interactive_paper::parser::{Sink, Tokenizer};
+    /// use std::sync::mpsc;
+    ///
+    /// let xml = r#"<?xml version="1.0"?>
+    /// <root>
+    /// <SECTION_A>
+    /// <section_number>Section A</section_number>
+    /// <item><question>Define osmosis.</question></item>
+    /// </SECTION_A>
+    /// </root>"#;
+    ///
+    /// let (tx, rx) = mpsc::channel();
+    /// Tokenizer::tokenize(vec![xml.to_string()], Sink::new(tx));
+    ///
+    /// let mut builder = QPaperBuilder::new();
+    /// for tag in rx {
+    ///     builder.process_tag(tag);
+    /// }
+    /// let paper = builder.end();
+    ///
+    /// let section = paper.nth(1).unwrap();
+    /// let question = paper.nth(section.raw().first_child.unwrap()).unwrap();
+    ///
+    /// assert_eq!(question.raw().parent, Some(1));
+    /// assert_eq!(question.data().question_number(), Some(1));
+    /// ```
     pub fn raw(&self) -> &Node{
         &self.question_paper.nodes[self.index()]
     }
@@ -126,6 +553,13 @@ pub trait Predicate {
     }
 }
 
+// closures can be used as predicates
+impl<F> Predicate for F where F: Fn(&NodeIndex) -> bool {
+    fn matches(&self, node: &NodeIndex) -> bool {
+        self(node)
+    }
+}
+
 // and two predicates
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct And<A, B>(pub A, pub B);
@@ -138,9 +572,59 @@ impl<A: Predicate, B: Predicate> Predicate for And<A, B> {
 
 
 // predicates to search the document
+/// Predicates for searching a paper's nodes.
+///
+/// Implement [`Predicate`] to search with your own criteria and pass it to `find_all` or `find_in_range`.
+///
+/// ```
+/// use interactive_paper::{QPaperBuilder, Builder};
+/// use interactive_paper::parser::{Sink, Tokenizer};
+/// use interactive_paper::question_paper::{NodeIndex, NodeData};
+/// use interactive_paper::question_paper::predicates::{Predicate, QuestionPredicate};
+/// use std::sync::mpsc;
+///
+/// // questions that ask the reader to define something
+/// struct Definition;
+///
+/// impl Predicate for Definition {
+///     fn matches(&self, node: &NodeIndex) -> bool {
+///         match node.data() {
+///             NodeData::Question(question) => question.question.starts_with("Define"),
+///             _ => false
+///         }
+///     }
+/// }
+///
+/// let xml = r#"<?xml version="1.0"?>
+/// <root>
+/// <SECTION_A>
+/// <section_number>Section A</section_number>
+/// <item><question>Name the parts of a cell.</question></item>
+/// <item><question>Define osmosis.</question></item>
+/// </SECTION_A>
+/// </root>"#;
+///
+/// let (tx, rx) = mpsc::channel();
+/// Tokenizer::tokenize(vec![xml.to_string()], Sink::new(tx));
+///
+/// let mut builder = QPaperBuilder::new();
+/// for tag in rx {
+///     builder.process_tag(tag);
+/// }
+/// let paper = builder.end();
+///
+/// let found: Vec<usize> = paper.find_all(Definition).iter().map(|node| node.index()).collect();
+/// assert_eq!(found, vec![3]);
+///
+/// // predicates combine with `and`
+/// assert_eq!(paper.find_all(QuestionPredicate.and(Definition)).len(), 1);
+/// ```
 pub mod predicates {
     use super::*;
 
+    pub use super::Predicate;
+
+    /// Matches question nodes
     #[derive(Copy, Clone, Debug, PartialEq)]
     pub struct QuestionPredicate;
 
@@ -150,6 +634,7 @@ pub mod predicates {
         }
     }
 
+    /// Matches section headers
     #[derive(Copy, Clone, Debug, PartialEq)]
     pub struct SectionPredicate;
 
@@ -158,4 +643,37 @@ pub mod predicates {
             other.data().is_section()
         }
     }
+
+    /// Matches the parts of multi-part questions
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    pub struct SubPartPredicate;
+
+    impl Predicate for SubPartPredicate {
+        fn matches(&self, other: &NodeIndex) -> bool {
+            other.data().is_sub_part()
+        }
+    }
+
+    /// Matches the question with the given printed number
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    pub struct QuestionNumberPredicate(pub u32);
+
+    impl Predicate for QuestionNumberPredicate {
+        fn matches(&self, other: &NodeIndex) -> bool {
+            other.data().question_number() == Some(self.0)
+        }
+    }
+
+    /// Matches questions of the given type
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    pub struct TypePredicate(pub QuestionType);
+
+    impl Predicate for TypePredicate {
+        fn matches(&self, other: &NodeIndex) -> bool {
+            match other.data() {
+                NodeData::Question(ref question) => question.question_type == Some(self.0),
+                _ => false
+            }
+        }
+    }
 }
\ No newline at end of file
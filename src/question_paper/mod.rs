@@ -1,41 +1,275 @@
 mod builder;
 mod interface;
+mod session;
 pub mod intents;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, BTreeMap, BTreeSet, VecDeque};
 use std::borrow::Cow::{Borrowed, self};
+use std::fmt;
+use std::sync::Arc;
+use std::ops::Range;
 
-use interface::{Node, Predicate, NodeIndex, NodeData, predicates, Note};
+use interface::Mark;
 
-use Reference::{Start, Current, End};
+pub use interface::{predicates, Node, NodeIndex, NodeData, Predicate, NodeId, Note, FullQuestion, Metadata, NodeStatus, QuestionType, Difficulty, SubPartData};
+pub use session::{SessionState, SessionDiff, FragmentError};
+
+use Reference::{Start, Current, End, Absolute};
 
 // re exports
-pub use builder::{QPaperBuilder, Builder};
-pub use intents::{Read, Write, Reference, Intent, Reader, Writer, WriteResult, ReadResult, IntentResult, MetaIntent};
+pub use builder::{QPaperBuilder, Builder, PaperSchema, SectionSchema, QuestionSchema};
+pub use intents::{Read, Write, Reference, Intent, Reader, Writer, WriteResult, ReadResult, ReadError, IntentResult, IntentResultRef, ReadOutcome, MetaIntent, NoteOrder, MarkReason, MetaPayload, Candidate, AnsweredQuestion};
 
 #[derive(Debug, Clone)]
 pub struct QuestionPaper {
-    pub nodes: Vec<Node>,
+    nodes: Vec<Node>,
     prev_index: usize,
-    last_index: usize,
+    max_reached: usize,
+    last_index: Option<usize>,
     total_questions: u32,
-    marked: HashMap<usize, NodeData>,
-    skipped: HashMap<usize, NodeData>,
-    notes: Vec<Note>
+    marked: HashMap<NodeId, Mark>,
+    marked_index: BTreeSet<usize>,
+    skipped: HashMap<NodeId, NodeData>,
+    notes: Vec<Note>,
+    /// Every answer given for each node, oldest first, the last is the current answer
+    answered: HashMap<NodeId, Vec<String>>,
+    presented: HashMap<NodeId, Vec<usize>>,
+    last_intent: Option<Intent>,
+    skip_answered: bool,
+    last_read: Option<NodeId>,
+    on_move: OnMove,
+    metadata: Metadata,
+    nearest_fallback: bool,
+    wrap: bool,
+    current_offset: usize,
+    clock: Clock,
+    entered_at: Option<u64>,
+    time_spent: HashMap<NodeId, u64>,
+    question_positions: Option<Vec<usize>>,
+    announce_sections: bool,
+    announced_section: Option<String>,
+    read_only: bool,
+    allow_skipping_answered: bool,
+    max_search_nodes: Option<usize>,
+    advance_after_answer: bool,
+    trace: Trace,
+    /// The position of the option last read on the current question, reset when the cursor moves
+    option_cursor: Option<usize>
+}
+
+/// A callback invoked with the new cursor index whenever the cursor moves
+pub type MoveCallback = Box<dyn FnMut(usize) + Send + Sync>;
+
+// holds the cursor callback, a cloned paper does not notify the original's listener
+#[derive(Default)]
+struct OnMove(Option<MoveCallback>);
+
+impl Clone for OnMove {
+    fn clone(&self) -> Self {
+        OnMove(None)
+    }
+}
+
+impl fmt::Debug for OnMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("OnMove(Some(..))"),
+            None => f.write_str("OnMove(None)")
+        }
+    }
+}
+
+/// A source of the current time in seconds, injected so time tracking can be tested
+pub type TimeSource = Arc<dyn Fn() -> u64 + Send + Sync>;
+
+// holds the time source, shared between copies of a paper
+#[derive(Default, Clone)]
+struct Clock(Option<TimeSource>);
+
+impl fmt::Debug for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("Clock(Some(..))"),
+            None => f.write_str("Clock(None)")
+        }
+    }
+}
+
+// whether an intent resolved without an error
+fn succeeded(result: &IntentResult) -> bool {
+    match result {
+        IntentResult::Read(result) => result.is_ok(),
+        IntentResult::FullQuestion(result) => result.is_ok(),
+        IntentResult::Candidates(result) => result.is_ok(),
+        IntentResult::Playback(result) => result.is_ok(),
+        IntentResult::Answered(result) => result.is_ok(),
+        IntentResult::Write(WriteResult::Success(_)) | IntentResult::Write(WriteResult::Advanced(..)) => true,
+        IntentResult::Write(WriteResult::Error(_)) => false,
+        IntentResult::Meta(_) | IntentResult::MetaData(_) => true
+    }
+}
+
+// index of the final question node
+fn last_question(nodes: &[Node]) -> Option<usize> {
+    nodes.iter().rposition(|node| node.data.is_question())
+}
+
+/// One resolved intent in the trace, with whether it succeeded
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    pub intent: Intent,
+    pub succeeded: bool
+}
+
+// a ring buffer of the most recent resolved intents, oldest first
+#[derive(Debug, Clone, Default)]
+struct Trace {
+    capacity: usize,
+    entries: VecDeque<TraceEntry>
+}
+
+impl Trace {
+    fn record(&mut self, intent: Intent, succeeded: bool) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.entries.push_back(TraceEntry { intent, succeeded });
+        self.shrink();
+    }
+
+    // drop the oldest entries beyond the capacity
+    fn shrink(&mut self) {
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+}
+
+/// How a notes transcript is laid out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptOptions {
+    /// The order the notes are read back in
+    pub order: NoteOrder,
+    /// Put between notes, a newline by default
+    pub separator: String,
+    /// Label each note with the question or section it was taken on
+    pub include_numbers: bool,
+    /// A line read before the notes, if any
+    pub header: Option<String>
+}
+
+impl Default for TranscriptOptions {
+    fn default() -> Self {
+        TranscriptOptions {
+            order: NoteOrder::default(),
+            separator: "\n".to_string(),
+            include_numbers: true,
+            header: None
+        }
+    }
+}
+
+/// A saved copy of the mutable paper state that can be rolled back to
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    prev_index: usize,
+    max_reached: usize,
+    marked: HashMap<NodeId, Mark>,
+    skipped: HashMap<NodeId, NodeData>,
+    notes: Vec<Note>,
+    answered: HashMap<NodeId, Vec<String>>,
+    presented: HashMap<NodeId, Vec<usize>>,
+    time_spent: HashMap<NodeId, u64>
 }
 
 
 
 impl QuestionPaper {
-    pub fn new(nodes: Vec<Node>, last_index: usize, total_questions: u32) -> Self {
+    /// Create a paper over built nodes. End references anchor on the final question, found from the nodes.
+    pub fn new(nodes: Vec<Node>, total_questions: u32) -> Self {
+        let last_index = last_question(&nodes);
+
         QuestionPaper {
             nodes,
             prev_index:0,
+            max_reached: 0,
             last_index,
             total_questions,
             marked: HashMap::new(),
+            marked_index: BTreeSet::new(),
             skipped: HashMap::new(),
-            notes: vec![]
+            notes: vec![],
+            answered: HashMap::new(),
+            presented: HashMap::new(),
+            last_intent: None,
+            skip_answered: false,
+            last_read: None,
+            on_move: OnMove::default(),
+            metadata: Metadata::default(),
+            nearest_fallback: false,
+            wrap: false,
+            current_offset: 1,
+            clock: Clock::default(),
+            entered_at: None,
+            time_spent: HashMap::new(),
+            question_positions: None,
+            announce_sections: false,
+            announced_section: None,
+            read_only: false,
+            allow_skipping_answered: false,
+            max_search_nodes: None,
+            advance_after_answer: false,
+            trace: Trace::default(),
+            option_cursor: None
+        }
+    }
+
+    /// Attach the paper's descriptive metadata
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    // read the metadata aloud, leaving out anything missing
+    fn info(&self) -> String {
+        let mut parts = vec![];
+
+        if let Some(ref title) = self.metadata.title {
+            parts.push(title.clone());
+        }
+
+        if let Some(ref subject) = self.metadata.subject {
+            parts.push(format!("Subject: {}", subject));
+        }
+
+        if let Some(minutes) = self.metadata.duration_minutes {
+            parts.push(format!("Duration: {} minutes", minutes));
+        }
+
+        if parts.is_empty(){
+            return "No information is available for this paper".to_string();
+        }
+
+        parts.join(". ")
+    }
+
+    // minutes left against the time limit, or the duration when no separate limit is given
+    fn time_remaining(&self, elapsed: u32) -> String {
+        let limit = match self.metadata.time_limit_minutes.or(self.metadata.duration_minutes) {
+            Some(limit) => limit,
+            None => return "This paper has no time limit".to_string()
+        };
+
+        match limit.saturating_sub(elapsed) {
+            0 => "Time is up".to_string(),
+            1 => "Hurry, only 1 minute remaining".to_string(),
+            remaining if remaining < 5 => format!("Hurry, only {} minutes remaining", remaining),
+            remaining => format!("{} minutes remaining", remaining)
         }
     }
 
@@ -45,274 +279,4582 @@ impl QuestionPaper {
             question_paper: self,
             predicate,
             next,
-            skip: skip -1
+            skip: skip.saturating_sub(1),
+            reversed: false,
+            skip_sections: false,
+            scanned: 0,
+            max_nodes: self.max_search_nodes
         }
     }
 
-    // return the nth node in this document
-    pub fn nth(&self, index: usize) -> Option<NodeIndex> {
-        NodeIndex::new(self, index)
+    // the node a reference starts searching from and how many matches it skips
+    fn anchor(&self, reference: &Reference) -> (usize, usize) {
+        // a cursor on a sub-part counts from the question it belongs to
+        self.anchor_from(reference, self.owning_question().unwrap_or(self.prev_index))
     }
 
-    // get the total number of nodes
-    pub fn len(&self) -> usize {
-        self.nodes.len()
+    // like `anchor`, with Current references counting from the given node
+    fn anchor_from(&self, reference: &Reference, cursor: usize) -> (usize, usize) {
+        match reference {
+            Start(skip) => (0, skip.unsigned_abs() as usize),
+            Current(skip) => (cursor, (skip.unsigned_abs() as usize).saturating_add(self.current_offset)),
+            // a paper without questions is searched back from its end
+            End(skip) => (self.last_index.unwrap_or(self.len()), skip.unsigned_abs() as usize),
+            Absolute(index) => (*index, 1)
+        }
     }
 
-    // get the previous index
-    pub fn prev_index(&self) -> usize {
-        self.prev_index
+    /// Count the matches from a reference onwards, in the direction the reference searches
+    pub fn count_from<P: Predicate>(&self, predicate: P, reference: &Reference) -> usize {
+        let (prev, skip) = self.anchor(reference);
+        let finder = self.find(predicate, prev, skip);
+
+        if reference.is_forward(){
+            finder.count()
+        }else{
+            finder.rev().count()
+        }
     }
 
-    pub fn last_index(&self) -> usize {
-        self.last_index 
+    /// Find every node matching a predicate, in document order
+    pub fn find_all<P: Predicate>(&self, predicate: P) -> Vec<NodeIndex<'_>> {
+        self.find_in_range(predicate, 0..self.len())
     }
 
-    pub fn update_previous(&mut self, index: usize) {
-        self.prev_index = index;
+    /// Find all nodes matching a predicate within a range of indices
+    pub fn find_in_range<P: Predicate>(&self, predicate: P, range: Range<usize>) -> Vec<NodeIndex<'_>> {
+        let end = range.end.min(self.len());
+
+        (range.start..end)
+            .filter_map(|index| self.nth(index))
+            .filter(|node| predicate.matches(node))
+            .collect()
     }
 
-    // resolve a read or write intent
-    pub fn resolve_intent(&mut self, intent: Intent) ->  IntentResult {
-        match intent {
-            Intent::ReadIntent(ref read_intent) => {
-                let result = match self.resolve_read_intent(read_intent) {
-                    Ok(node) => {
-                        let index = node.index;
-        
-                        self.update_previous(index);
-        
-                        Ok(node.data.clone())
-                    },
-                    Err(e) => Err(e)
-                };
+    // index of the first section header after a node, or the end of the paper
+    fn next_section_boundary(&self, index: usize) -> usize {
+        if let Some(header) = self.find(predicates::SectionPredicate, index + 1, 1).next() {
+            return header.index();
+        }
 
-                IntentResult::Read(result)
-            },
-            Intent::WriteIntent(ref write_intent) => {
-                let result = self.resolve_write_intent(write_intent);
+        // without header nodes the next section starts where the questions change section
+        let name = self.built_section(index);
 
-                IntentResult::Write(result)
-            },
-            Intent::Meta(ref meta) => {
-                match meta {
-                    MetaIntent::Marked => {
-                        // get the total marked and read them out
-                        let total_marked = self.marked.len();
+        (index + 1..self.len())
+            .find(|next| self.nodes[*next].data.is_question() && self.built_section(*next) != name)
+            .unwrap_or_else(|| self.len())
+    }
 
-                        return IntentResult::Meta(format!("You have {} marked questions", total_marked));
-                    },
-                    MetaIntent::Skipped => {
-                        return IntentResult::Meta(format!("You have skipped {} question", self.skipped.len()));
-                    }
-                }
-            }
+    // index of the nearest section header at or before a node, or the first question of its section
+    // when the paper was built without header nodes
+    fn section_header_before(&self, index: usize) -> Option<usize> {
+        let end = index.saturating_add(1).min(self.len());
+
+        if let Some(header) = self.nodes[..end].iter().rposition(|node| node.data.is_section()) {
+            return Some(header);
         }
-        
-    }
 
-    /// Check how many questions have been marked for review
-    pub fn num_marked(&self) -> usize {
-        self.marked.len()
+        let name = self.built_section(end.checked_sub(1)?)?;
+
+        (0..end).rev()
+            .take_while(|before| self.built_section(*before) == Some(name))
+            .last()
     }
 
-    pub fn total_questions(&self) -> u32 {
-        self.total_questions
+    // the section a node was built under when its header was left out of the nodes
+    fn built_section(&self, index: usize) -> Option<&str> {
+        let node = &self.nodes[index];
+
+        match node.data {
+            NodeData::Question(ref question) => question.section.as_deref(),
+            NodeData::SubPart(_) => node.parent.and_then(|parent| self.built_section(parent)),
+            _ => None
+        }
     }
 
-    pub fn num_skipped(&self) -> usize {
-        self.skipped.len()
+    // where each section starts, its header or its first question when there are no header nodes
+    fn section_starts(&self) -> Vec<usize> {
+        let headers: Vec<usize> = self.find_all(predicates::SectionPredicate)
+            .iter()
+            .map(|header| header.index())
+            .collect();
+
+        if !headers.is_empty(){
+            return headers;
+        }
+
+        (0..self.len())
+            .filter(|index| self.built_section(*index).is_some() && self.section_header_before(*index) == Some(*index))
+            .collect()
     }
 
-    /// get the notes
-    pub fn notes(&self) -> &Vec<Note> {
-        &self.notes
+    /// Each question's printed number with its content, in document order
+    pub fn numbered_questions(&self) -> impl Iterator<Item = (u32, NodeData)> + '_ {
+        self.nodes.iter()
+            .filter_map(|node| node.data.question_number().map(|number| (number, node.data.clone())))
     }
 
-    // resolve the read intent in 
-    fn find_node(&mut self, reads: &Vec<Read>) -> ReadResult {
-        let mut node = None;
+    // every question from a reference to the end, leaving the cursor on the last one
+    fn read_from(&mut self, reference: &Reference) -> Result<Vec<NodeData>, ReadError> {
+        let first = self.resolve_question(reference)?;
 
-        for intent in reads {
-            node = Some(self.resolve_read_intent(intent));
-        }
+        let questions: Vec<Node> = self.find_in_range(predicates::QuestionPredicate, first.index..self.len())
+            .iter()
+            .map(|node| node.raw().clone())
+            .collect();
 
-        if node.is_none(){
-            return Err(Borrowed("Could not find the specified request"));
+        if let Some(last) = questions.last() {
+            self.visit(last);
         }
 
-        node.unwrap()
+        Ok(questions.into_iter().map(|node| node.data).collect())
     }
 
-}
+    /// How many questions lie between two indices, counting the target but not the start, negative going back
+    pub fn question_distance(&self, from: usize, to: usize) -> isize {
+        let between = |range: Range<usize>| self.find_in_range(predicates::QuestionPredicate, range).len() as isize;
 
-impl Reader for QuestionPaper {
-    /// Resolves a read intent
-    fn resolve_read_intent(&mut self, read_intent: &Read) -> ReadResult {
-        match read_intent {
-            Read::Question(ref question) => self.resolve_question(question),
-            Read::Section(ref section) => self.resolve_section(section),
+        if to >= from {
+            between(from + 1..to + 1)
+        }else{
+            -between(to..from)
         }
     }
 
-    /// Resolve a question
-    fn resolve_question(&mut self, reference: &Reference) -> ReadResult {
-        let predicate = predicates::QuestionPredicate;
-
-        self.resolve_referece(reference, predicate)
+    /// Whether a node is marked, skipped, answered or has a note
+    pub fn status_of(&self, id: NodeId) -> NodeStatus {
+        NodeStatus {
+            marked: self.marked.contains_key(&id),
+            skipped: self.skipped.contains_key(&id),
+            answered: self.answered.contains_key(&id),
+            has_note: self.notes.iter().any(|note| note.id == id)
+        }
     }
 
-    /// Resolve a section
-    fn resolve_section(&mut self, reference: &Reference) -> ReadResult {
-        let predicate = predicates::SectionPredicate;
+    /// The index and name of the section a node belongs to, `None` before the first section.
+    ///
+    /// The index is the section's header, or its first question when the paper has no header nodes.
+    pub fn section_of(&self, index: usize) -> Option<(usize, String)> {
+        let header = self.section_header_before(index)?;
+        let name = self.nodes[header].data.section_name().or_else(|| self.built_section(header))?;
 
-        self.resolve_referece(reference, predicate)
+        Some((header, name.to_string()))
     }
 
-    /// Resolve from a reference
-    fn resolve_referece<P: Predicate>(&mut self, reference: &Reference, predicate: P) -> ReadResult {
+    // all questions with a printed number, with the section each is in
+    fn numbered_candidates(&self, number: u32) -> Result<Vec<Candidate>, Cow<'static, str>> {
+        let candidates: Vec<Candidate> = self.find_all(predicates::QuestionNumberPredicate(number))
+            .iter()
+            .map(|node| Candidate {
+                index: node.index(),
+                section: self.section_of(node.index()).map(|(_, name)| name),
+                data: node.data().clone()
+            })
+            .collect();
 
-        let (prev, skip) = match reference {
-            Start(skip) => (0, skip.abs() as usize),
-            Current(skip) => (self.prev_index(), (skip.abs() as usize) + 1),
-            End(skip) => (self.last_index(), skip.abs() as usize)
-            
-        };
-        
+        if candidates.is_empty(){
+            return Err(Cow::Owned(format!("There is no question {}", number)));
+        }
 
-        self.resolve(predicate, prev, skip, reference)
+        Ok(candidates)
     }
 
-    fn resolve<P: Predicate>(&mut self, predicate: P, prev: usize, skip: usize, reference: &Reference) -> ReadResult {
-        let finder = self.find(predicate, prev, skip);
+    /// Each section's name with its answered and total question counts
+    pub fn section_progress(&self) -> Vec<(String, usize, usize)> {
+        let mut answered: HashMap<usize, usize> = HashMap::new();
 
+        for id in self.answered.keys() {
+            let header = self.index_of(*id)
+                .filter(|index| self.nodes[*index].data.is_question())
+                .and_then(|index| self.section_header_before(index));
 
-        if reference.is_forward(){
-            self.find_next(finder)
-        }else{
-            self.find_back(finder)
+            if let Some(header) = header {
+                *answered.entry(header).or_insert(0) += 1;
+            }
         }
+
+        self.section_starts()
+            .into_iter()
+            .map(|index| {
+                let end = self.next_section_boundary(index);
+                let total = self.find_in_range(predicates::QuestionPredicate, index..end).len();
+                let name = self.section_of(index).map(|(_, name)| name).unwrap_or_default();
+
+                (name, answered.get(&index).cloned().unwrap_or(0), total)
+            })
+            .collect()
     }
 
-    /// Do a foward find
-    fn find_next<P: Predicate>(&self, mut finder: Find<P>) -> ReadResult {
-        if let Some(node) = finder.next(){
-            Ok(node.raw().clone())
-        }else{
-            Err(Borrowed("Could not find a next node"))
-        }
+    /// Each section's name with how many questions it holds, in document order
+    pub fn section_question_counts(&self) -> Vec<(String, usize)> {
+        self.section_starts()
+            .into_iter()
+            .map(|index| {
+                let end = self.next_section_boundary(index);
+                let name = self.section_of(index).map(|(_, name)| name).unwrap_or_default();
+
+                (name, self.find_in_range(predicates::QuestionPredicate, index..end).len())
+            })
+            .collect()
     }
 
-    /// Do a reverse find
-    fn find_back<P: Predicate>(&self, mut finder: Find<P>) -> ReadResult {
-        if let Some(node) = finder.next_back(){
-            Ok(node.raw().clone())
-        }else{
-            Err(Borrowed("Could not resolve a previous node"))
+    /// Rank section names by how closely they match a spoken query, best match first
+    ///
+    /// Scores are a normalized edit distance in `[0, 1]`, where 1 is an exact match
+    pub fn match_sections(&self, query: &str) -> Vec<(String, f32)> {
+        let query = query.to_lowercase();
+
+        let mut candidates: Vec<(String, f32)> = self.nodes.iter()
+            .filter_map(|node| match node.data {
+                NodeData::Section(ref section) => Some(section.section_name.clone()),
+                _ => None
+            })
+            .map(|name| {
+                let score = similarity(&query, &name.to_lowercase());
+                (name, score)
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        candidates
+    }
+
+    /// Questions whose text matches a search term, most relevant first
+    ///
+    /// Each word of the term scores its closest word in the question, exact words score 1 and
+    /// near misses the edit similarity when that is at least `SEARCH_THRESHOLD`. A question's
+    /// relevance is the average over the term's words, ties keep document order.
+    pub fn search(&self, term: &str) -> Vec<(usize, NodeData)> {
+        let terms = words(term);
+        if terms.is_empty(){
+            return vec![];
         }
+
+        let mut matches: Vec<(usize, f32)> = self.nodes.iter()
+            .enumerate()
+            .filter_map(|(index, node)| match node.data {
+                NodeData::Question(ref question) => Some((index, words(&question.question))),
+                _ => None
+            })
+            .map(|(index, text)| {
+                let total: f32 = terms.iter()
+                    .map(|term| {
+                        text.iter()
+                            .map(|word| similarity(term, word))
+                            .filter(|score| *score >= SEARCH_THRESHOLD)
+                            .fold(0.0, f32::max)
+                    })
+                    .sum();
+
+                (index, total / terms.len() as f32)
+            })
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+
+        matches.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        matches.into_iter()
+            .map(|(index, _)| (index, self.nodes[index].data.clone()))
+            .collect()
     }
 
-}
+    /// Count the questions after the cursor up to the next section header
+    pub fn remaining_in_section(&self) -> usize {
+        let start = self.prev_index + 1;
+        let end = self.next_section_boundary(self.prev_index);
 
-impl Writer for QuestionPaper {
-    /// Resolve a write intent
-    fn resolve_write_intent(&mut self, write_intent: &Write) ->  WriteResult{
-        match write_intent {
-            Write::Mark(ref read_intents) => return self.mark_for_review(read_intents),
-            Write::Skip(ref read_intents) => self.skip(read_intents),
-            Write::Note(ref read_intents, note) => self.note(read_intents, note.to_string())
-        }
+        self.find_in_range(predicates::QuestionPredicate, start..end).len()
     }
 
+    /// The questions in the current section that have not been answered yet
+    pub fn unanswered_in_section(&self) -> Vec<NodeData> {
+        let header = match self.section_header_before(self.prev_index) {
+            Some(header) => header,
+            None => return vec![]
+        };
+        let end = self.next_section_boundary(header);
 
-    // process a read intent and mark it for review
-    fn mark_for_review(&mut self, reads: &Vec<Read>) -> WriteResult {
-        if let Ok(node) = self.find_node(reads) {
-            self.marked.insert(node.index, node.data.clone());
-            
+        self.find_in_range(predicates::QuestionPredicate, header..end)
+            .iter()
+            .filter(|node| !self.answered.contains_key(&node.raw().id))
+            .map(|node| node.data().clone())
+            .collect()
+    }
 
-            return WriteResult::Success("Question has been marked for review".to_string());
-        }
-        
-        return WriteResult::Error(Borrowed("Could not mark the specified item for review. Please try again"));        
+    /// The question closest to an index in either direction, preferring the later one on a tie
+    pub fn nearest_question(&self, index: usize) -> Option<NodeIndex<'_>> {
+        let is_question = |index: usize| self.nth(index).filter(|node| node.data().is_question());
+
+        (0..self.len().max(index.saturating_add(1))).find_map(|distance| {
+            index.checked_add(distance).and_then(is_question)
+                .or_else(|| index.checked_sub(distance).and_then(is_question))
+        })
     }
 
-    fn skip(&mut self, reads: &Vec<Read>) -> WriteResult {
-        if let Ok(node) = self.find_node(reads) {
-            self.skipped.insert(node.index, node.data.clone());
+    // return the nth node in this document
+    pub fn nth(&self, index: usize) -> Option<NodeIndex> {
+        NodeIndex::new(self, index)
+    }
 
-            return WriteResult::Success("Question has been skipped".to_string());
-        }
-        
-        return WriteResult::Error(Borrowed("Could not skip the specified item. Please try again"));        
+    /// The nodes in document order
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
     }
 
-    /// Take a note on this node
-    fn note(&mut self, reads: &Vec<Read>, note: String) -> WriteResult {
-        if let Ok(node) = self.find_node(reads){
-            self.notes.push(Note {
-                note,
-                index: node.index
-            });
-           
-            return WriteResult::Success("A not has been taken".to_string());
+    /// Edit the nodes directly. Everything cached by node position is rebuilt once the edit is done.
+    pub fn edit_nodes<F, R>(&mut self, edit: F) -> R where F: FnOnce(&mut Vec<Node>) -> R {
+        let edited = edit(&mut self.nodes);
+        self.nodes_changed();
+
+        edited
+    }
+
+    // rebuild the caches that hold node positions after the nodes were edited
+    fn nodes_changed(&mut self) {
+        for (index, node) in self.nodes.iter_mut().enumerate() {
+            node.index = index;
         }
 
-        return WriteResult::Error(Borrowed("Could not take a note as requested"));
+        self.question_positions = None;
+        self.last_index = last_question(&self.nodes);
+        self.reindex_marks();
+        self.prev_index = self.prev_index.min(self.len().saturating_sub(1));
+        self.max_reached = self.max_reached.min(self.len().saturating_sub(1));
+    }
 
-        
+    // the sorted positions of marked nodes, from the marks themselves
+    fn reindex_marks(&mut self) {
+        self.marked_index = self.indices_of(self.marked.keys()).into_iter().collect();
     }
-}
 
+    // index of the nth question from the end, where 1 is the last, from the cached question positions
+    fn question_position_from_end(&mut self, n: usize) -> Option<usize> {
+        let nodes = &self.nodes;
+        let positions = self.question_positions.get_or_insert_with(|| {
+            (0..nodes.len()).filter(|index| nodes[*index].data.is_question()).collect()
+        });
 
-pub struct Find<'a, P:Predicate> {
-    predicate: P,
-    next: usize,
-    question_paper: &'a QuestionPaper,
-    skip: usize
-}
+        positions.get(positions.len().checked_sub(n.max(1))?).cloned()
+    }
 
-impl <'a, P: Predicate> Iterator for Find<'a, P> {
-    type Item = NodeIndex<'a>;
+    /// The nth question counting back from the end, where 1 is the last
+    pub fn nth_question_from_end(&mut self, n: usize) -> Option<NodeIndex<'_>> {
+        let index = self.question_position_from_end(n)?;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        while self.next < self.question_paper.len(){
-            let node = self.question_paper.nth(self.next).unwrap();
+        self.nth(index)
+    }
 
-            self.next += 1;
+    // get the total number of nodes
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
 
-            if self.predicate.matches(&node){
-                if self.skip >= 1 {  
-                   
-                    self.skip -= 1;
-                }else{
-                    return Some(node);
-                }
-            }
-        }
+    /// Look up the current position of a node from its stable id
+    pub fn index_of(&self, id: NodeId) -> Option<usize> {
+        self.nodes.iter().position(|node| node.id == id)
+    }
 
-        None
+    // get the previous index
+    pub fn prev_index(&self) -> usize {
+        self.prev_index
     }
-}
 
+    /// The index of the final question, `None` when the paper has no questions
+    pub fn last_index(&self) -> Option<usize> {
+        self.last_index
+    }
 
-impl<'a, P: Predicate> DoubleEndedIterator for Find<'a, P> {
-    fn next_back(&mut self) -> Option<NodeIndex<'a>> {
-        while self.next > 0 {
-            let node = self.question_paper.nth(self.next).unwrap();
+    /// The node returned by the last successful read, if any
+    pub fn last_read(&self) -> Option<NodeIndex<'_>> {
+        self.last_read
+            .and_then(|id| self.index_of(id))
+            .and_then(|index| self.nth(index))
+    }
 
-            self.next -= 1;
+    pub fn update_previous(&mut self, index: usize) {
+        if index == self.prev_index {
+            return;
+        }
 
-            if self.predicate.matches(&node) {
-                if self.skip >= 1 {
-                    self.skip -= 1;
-                }else{
-                    return Some(node);
+        if let Some(ref now) = self.clock.0 {
+            let now = now();
+
+            // charge the time since the cursor arrived to the node it is leaving
+            if let (Some(entered_at), Some(node)) = (self.entered_at, self.nodes.get(self.prev_index)) {
+                *self.time_spent.entry(node.id).or_insert(0) += now.saturating_sub(entered_at);
+            }
+
+            self.entered_at = Some(now);
+        }
+
+        self.prev_index = index;
+        self.max_reached = self.max_reached.max(index);
+        self.option_cursor = None;
+
+        if let Some(ref mut on_move) = self.on_move.0 {
+            on_move(index);
+        }
+    }
+
+    /// Track time per node with the given clock, in seconds. Time is charged when the cursor leaves a node.
+    pub fn set_clock<F>(&mut self, now: F) where F: Fn() -> u64 + Send + Sync + 'static {
+        self.clock = Clock(Some(Arc::new(now)));
+        self.entered_at = None;
+    }
+
+    /// Seconds spent on the node at an index
+    pub fn time_on(&self, index: usize) -> u64 {
+        self.nodes.get(index)
+            .and_then(|node| self.time_spent.get(&node.id))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    // the three questions with the most time spent, slowest first
+    fn slowest_questions(&self) -> String {
+        let mut spent: Vec<(usize, u64)> = self.time_spent.iter()
+            .filter(|(_, seconds)| **seconds > 0)
+            .filter_map(|(id, seconds)| self.index_of(*id).map(|index| (index, *seconds)))
+            .filter(|(index, _)| self.nodes[*index].data.is_question())
+            .collect();
+
+        if spent.is_empty(){
+            return "No time has been recorded yet".to_string();
+        }
+
+        spent.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let slowest: Vec<String> = spent.iter()
+            .take(3)
+            .filter_map(|(index, seconds)| {
+                self.nodes[*index].data.question_number().map(|number| format!("question {} ({} seconds)", number, seconds))
+            })
+            .collect();
+
+        format!("Most time went on {}", slowest.join(", "))
+    }
+
+    // move the cursor onto a node that has just been read
+    fn visit(&mut self, node: &Node) {
+        self.visit_at(node.index);
+    }
+
+    fn visit_at(&mut self, index: usize) {
+        self.update_previous(index);
+        self.last_read = Some(self.nodes[index].id);
+    }
+
+    /// Go back to the question before the current one
+    pub fn previous_question(&mut self) -> ReadResult {
+        let node = self.resolve_question(&Reference::previous())
+            .map_err(|_| Borrowed("You are already at the first question"))?;
+
+        self.visit(&node);
+
+        Ok(node)
+    }
+
+    /// Go back to the header of the section before the current one
+    pub fn previous_section(&mut self) -> ReadResult {
+        let target = self.section_header_before(self.prev_index)
+            .and_then(|current| current.checked_sub(1))
+            .and_then(|before| self.section_header_before(before));
+
+        self.move_to(target, Borrowed("You are already in the first section"))
+    }
+
+    /// When enabled, review navigation wraps around to the start of the paper
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
+    /// Move to the next marked question after the cursor
+    pub fn next_marked(&mut self) -> ReadResult {
+        if self.marked_index.is_empty(){
+            return Err("You have not marked any questions".into());
+        }
+
+        let next = self.marked_index.range(self.prev_index + 1..).next()
+            .or_else(|| if self.wrap { self.marked_index.iter().next() } else { None })
+            .cloned();
+
+        self.move_to(next, Borrowed("There are no more marked questions"))
+    }
+
+    /// Move to the next question after the cursor that has not been marked, skipped or answered
+    pub fn next_fresh(&mut self) -> ReadResult {
+        let (marked, skipped, answered) = (&self.marked, &self.skipped, &self.answered);
+
+        let predicate = predicates::QuestionPredicate.and(|node: &NodeIndex| {
+            let id = node.raw().id;
+
+            !(marked.contains_key(&id) || skipped.contains_key(&id) || answered.contains_key(&id))
+        });
+
+        let next = self.find(predicate, self.prev_index + 1, 1)
+            .next()
+            .map(|node| node.index());
+
+        self.move_to(next, Borrowed("Nothing is left untouched"))
+    }
+
+    /// Leave review and carry on from the question after the last marked one, or the first question when none are marked
+    pub fn resume_after_marked(&mut self) -> ReadResult {
+        let from = self.marked_index.iter().next_back().map_or(0, |index| index + 1);
+
+        let next = self.find(predicates::QuestionPredicate, from, 1)
+            .next()
+            .map(|node| node.index());
+
+        self.move_to(next, Borrowed("There are no questions after the last marked one"))
+    }
+
+    // the question the cursor is on, or the question owning the part the cursor is on
+    fn owning_question(&self) -> Option<usize> {
+        let node = &self.nodes[self.prev_index];
+
+        match node.data {
+            NodeData::Question(_) => Some(self.prev_index),
+            NodeData::SubPart(_) => node.parent,
+            _ => None
+        }
+    }
+
+    // the next or previous part of the question at the cursor
+    fn step_part(&mut self, forward: bool) -> ReadResult {
+        let owner = match self.owning_question() {
+            Some(owner) => owner,
+            None => return Err("Read a question before moving between its parts".into())
+        };
+
+        let prev_index = self.prev_index;
+        let is_sibling = |index: &usize| {
+            self.nodes[*index].data.is_sub_part() && self.nodes[*index].parent == Some(owner)
+        };
+
+        let next = if forward {
+            (prev_index + 1..self.len()).find(is_sibling)
+        }else{
+            (owner..prev_index).rev().find(is_sibling)
+        };
+
+        let missing = if forward { "There are no more parts to this question" } else { "This is the first part of the question" };
+
+        self.move_to(next, Borrowed(missing))
+    }
+
+    /// Move to the next part of the question at the cursor
+    pub fn next_part(&mut self) -> ReadResult {
+        self.step_part(true)
+    }
+
+    /// Move to the previous part of the question at the cursor
+    pub fn previous_part(&mut self) -> ReadResult {
+        self.step_part(false)
+    }
+
+    // the next or previous option of the question at the cursor, in the order it was presented
+    fn step_option(&mut self, forward: bool) -> Result<String, Cow<'static, str>> {
+        let (question, id) = match self.owning_question().map(|owner| &self.nodes[owner]) {
+            Some(Node { data: NodeData::Question(ref question), id, .. }) => (question, *id),
+            _ => return Err(Borrowed("Read a question before moving between its options"))
+        };
+
+        if question.options.is_empty(){
+            return Err(Borrowed("This question has no options"));
+        }
+
+        let position = match (self.option_cursor, forward) {
+            (None, true) => 0,
+            (None, false) => return Err(Borrowed("You are already at the first option")),
+            (Some(position), true) if position + 1 < question.options.len() => position + 1,
+            (Some(_), true) => return Err(Borrowed("There are no more options")),
+            (Some(0), false) => return Err(Borrowed("You are already at the first option")),
+            (Some(position), false) => position - 1
+        };
+
+        // a shuffled question steps through its options as they were read out
+        let shown = self.presented.get(&id)
+            .and_then(|order| order.get(position).cloned())
+            .unwrap_or(position);
+        let option = question.options[shown].clone();
+
+        self.option_cursor = Some(position);
+
+        Ok(option)
+    }
+
+    /// Read the next option of the question at the cursor, starting from the first
+    pub fn next_option(&mut self) -> Result<String, Cow<'static, str>> {
+        self.step_option(true)
+    }
+
+    /// Read the option before the one last read
+    pub fn previous_option(&mut self) -> Result<String, Cow<'static, str>> {
+        self.step_option(false)
+    }
+
+    /// Take a note on the node at the cursor without resolving a reference again
+    pub fn note_current(&mut self, text: String) -> WriteResult {
+        if let Some(refusal) = self.read_only_refusal() {
+            return refusal;
+        }
+
+        if self.last_read.is_none(){
+            return WriteResult::Error(Borrowed("Read a question before taking a note on it"));
+        }
+
+        let id = self.nodes[self.prev_index].id;
+
+        match self.attach_note(id, text) {
+            Ok(()) => WriteResult::Success("A note has been taken".to_string()),
+            Err(message) => WriteResult::Error(message)
+        }
+    }
+
+    /// Move to the question roughly `percent` of the way through the paper, 0 being the first and 100 the last
+    pub fn jump_to_percent(&mut self, percent: u8) -> ReadResult {
+        let questions: Vec<usize> = self.find_all(predicates::QuestionPredicate)
+            .iter()
+            .map(|node| node.index())
+            .collect();
+
+        let last = match questions.len().checked_sub(1) {
+            Some(last) => last,
+            None => return Err("This paper has no questions".into())
+        };
+
+        let percent = usize::from(percent.min(100));
+        let position = (last * percent + 50) / 100;
+
+        self.move_to(Some(questions[position]), Borrowed("This paper has no questions"))
+    }
+
+    /// Mark the node at the cursor for review without resolving a reference again
+    pub fn mark_current(&mut self) -> WriteResult {
+        if let Some(refusal) = self.read_only_refusal() {
+            return refusal;
+        }
+
+        if self.last_read.is_none(){
+            return WriteResult::Error(Borrowed("Read a question before marking it"));
+        }
+
+        let node = &self.nodes[self.prev_index];
+        let (id, data) = (node.id, node.data.clone());
+
+        self.insert_mark(id, Mark {
+            data,
+            reason: MarkReason::default()
+        });
+
+        WriteResult::Success("Question has been marked for review".to_string())
+    }
+
+    /// Move back to the question the most recent note was taken on
+    pub fn jump_to_last_note(&mut self) -> ReadResult {
+        let index = self.notes.iter()
+            .rev()
+            .filter(|note| note.id != NodeId::GLOBAL)
+            .find_map(|note| self.index_of(note.id));
+
+        self.move_to(index, Borrowed("You have not taken any notes"))
+    }
+
+    /// One page of the marked questions in document order, empty past the last page
+    pub fn marked_page(&self, page: usize, per_page: usize) -> Vec<NodeData> {
+        self.marked_index.iter()
+            .skip(page.saturating_mul(per_page))
+            .take(per_page)
+            .map(|index| self.nodes[*index].data.clone())
+            .collect()
+    }
+
+    /// The marked questions' printed numbers, sorted and comma separated
+    pub fn marked_numbers_csv(&self) -> String {
+        self.numbers_csv(self.marked.keys())
+    }
+
+    /// The skipped questions' printed numbers, sorted and comma separated
+    pub fn skipped_numbers_csv(&self) -> String {
+        self.numbers_csv(self.skipped.keys())
+    }
+
+    // sorted question numbers for a set of nodes
+    fn numbers_csv<'a, I>(&self, ids: I) -> String where I: Iterator<Item = &'a NodeId> {
+        let mut numbers: Vec<u32> = ids
+            .filter_map(|id| self.index_of(*id))
+            .filter_map(|index| self.nodes[index].data.question_number())
+            .collect();
+        numbers.sort_unstable();
+
+        numbers.iter().map(|number| number.to_string()).collect::<Vec<_>>().join(",")
+    }
+
+    /// Questions grouped by difficulty in document order, questions without a difficulty are left out
+    pub fn by_difficulty(&self) -> HashMap<Difficulty, Vec<NodeData>> {
+        let mut groups: HashMap<Difficulty, Vec<NodeData>> = HashMap::new();
+
+        for node in &self.nodes {
+            if let NodeData::Question(ref question) = node.data {
+                if let Some(difficulty) = question.difficulty {
+                    groups.entry(difficulty).or_default().push(node.data.clone());
                 }
-               
             }
         }
 
+        groups
+    }
+
+    /// Whether every question has an answer recorded, section headers are not counted
+    pub fn is_complete(&self) -> bool {
+        self.nodes.iter()
+            .filter(|node| node.data.is_question())
+            .all(|node| self.answered.contains_key(&node.id))
+    }
+
+    /// Drop marks, skips, notes and answers on nodes that are no longer in the paper, returning how many were dropped.
+    /// Time recorded on those nodes goes too but is not counted.
+    pub fn prune_invalid(&mut self) -> usize {
+        let live: BTreeSet<NodeId> = self.nodes.iter().map(|node| node.id).collect();
+        let before = self.marked.len() + self.skipped.len() + self.notes.len() + self.answered.len();
+
+        self.marked.retain(|id, _| live.contains(id));
+        self.skipped.retain(|id, _| live.contains(id));
+        self.answered.retain(|id, _| live.contains(id));
+        self.notes.retain(|note| note.id == NodeId::GLOBAL || live.contains(&note.id));
+        self.time_spent.retain(|id, _| live.contains(id));
+        self.reindex_marks();
+
+        before - (self.marked.len() + self.skipped.len() + self.notes.len() + self.answered.len())
+    }
+
+    /// Unmark a node, returning whether it was marked. A read-only paper keeps its marks.
+    pub fn unmark(&mut self, id: NodeId) -> bool {
+        if self.read_only_refusal().is_some() || self.marked.remove(&id).is_none(){
+            return false;
+        }
+
+        if let Some(index) = self.index_of(id) {
+            self.marked_index.remove(&index);
+        }
+
+        true
+    }
+
+    /// Remove every mark, a read-only paper keeps its marks
+    pub fn clear_marks(&mut self) {
+        if self.read_only_refusal().is_some() {
+            return;
+        }
+
+        self.marked.clear();
+        self.marked_index.clear();
+    }
+
+    // record a mark and keep the sorted index of marked nodes in step
+    fn insert_mark(&mut self, id: NodeId, mark: Mark) {
+        if let Some(index) = self.index_of(id) {
+            self.marked_index.insert(index);
+        }
+
+        self.marked.insert(id, mark);
+    }
+
+    /// Marked and skipped questions together, in document order
+    pub fn review_queue(&self) -> Vec<usize> {
+        let mut queue = self.indices_of(self.marked.keys().chain(self.skipped.keys()));
+        queue.dedup();
+
+        queue
+    }
+
+    /// Move to the next question in the review queue after the cursor
+    pub fn next_in_review_queue(&mut self) -> ReadResult {
+        let queue = self.review_queue();
+
+        self.next_of(
+            queue,
+            Borrowed("There is nothing to come back to"),
+            Borrowed("There is nothing more to come back to")
+        )
+    }
+
+    // move to the first of a sorted set of indices after the cursor, wrapping if enabled
+    fn next_of(&mut self, indices: Vec<usize>, empty: Cow<'static, str>, exhausted: Cow<'static, str>) -> ReadResult {
+        if indices.is_empty(){
+            return Err(empty.into());
+        }
+
+        let next = indices.iter()
+            .find(|index| **index > self.prev_index)
+            .or_else(|| if self.wrap { indices.first() } else { None })
+            .cloned();
+
+        self.move_to(next, exhausted)
+    }
+
+    // visit the node at an index if there is one
+    fn move_to(&mut self, index: Option<usize>, missing: Cow<'static, str>) -> ReadResult {
+        match index {
+            Some(index) => {
+                let node = self.nodes[index].clone();
+                self.visit(&node);
+
+                Ok(node)
+            },
+            None => Err(missing.into())
+        }
+    }
+
+    /// Register a callback to be told whenever the cursor moves
+    pub fn set_on_move<F>(&mut self, on_move: F) where F: FnMut(usize) + Send + Sync + 'static {
+        self.on_move = OnMove(Some(Box::new(on_move)));
+    }
+
+    // resolve a read or write intent
+    pub fn resolve_intent(&mut self, intent: Intent) ->  IntentResult {
+        let result = self.dispatch_intent(intent.clone());
+        self.trace.record(intent.clone(), succeeded(&result));
+
+        // a successful read is remembered against the node it landed on
+        self.last_intent = Some(match intent {
+            Intent::ReadIntent(ref read) if succeeded(&result) => Intent::ReadIntent(read.pinned(self.prev_index)),
+            other => other
+        });
+
+        result
+    }
+
+    /// Resolve an intent like `resolve_intent`, borrowing the node read rather than cloning it
+    pub fn resolve_intent_ref(&mut self, intent: &Intent) -> IntentResultRef<'_> {
+        let (read, located) = match intent {
+            Intent::ReadIntent(ref read @ Read::Question(ref reference)) => (read, self.locate_question(reference)),
+            Intent::ReadIntent(ref read @ Read::Section(ref reference)) => (read, self.locate_reference(reference, predicates::SectionPredicate)),
+            other => return IntentResultRef::Owned(self.resolve_intent(other.clone()))
+        };
+
+        let from = self.prev_index;
+        self.trace.record(intent.clone(), located.is_ok());
+
+        match located {
+            Ok(index) => {
+                self.presented.remove(&self.nodes[index].id);
+                self.visit_at(index);
+                self.last_intent = Some(Intent::ReadIntent(read.pinned(index)));
+
+                let status = self.status_of(self.nodes[index].id);
+                let section = self.announce_section(index);
+                let distance = self.question_distance(from, index);
+
+                IntentResultRef::Read(Ok(ReadOutcome { data: &self.nodes[index].data, status, distance, section }))
+            },
+            Err(e) => {
+                self.last_intent = Some(intent.clone());
+
+                IntentResultRef::Read(Err(e))
+            }
+        }
+    }
+
+    /// Resolve the most recent intent again, reads re-read the same node rather than moving on
+    pub fn repeat_last(&mut self) -> IntentResult {
+        match self.last_intent.clone() {
+            Some(intent) => self.resolve_intent(intent),
+            None => IntentResult::Meta("There is nothing to repeat".to_string())
+        }
+    }
+
+    fn dispatch_intent(&mut self, intent: Intent) -> IntentResult {
+        match intent {
+            Intent::ReadIntent(Read::NumberedQuestions(number)) => {
+                IntentResult::Candidates(self.numbered_candidates(number))
+            },
+            Intent::ReadIntent(Read::ShuffledQuestion(ref reference, seed)) => {
+                IntentResult::FullQuestion(self.read_shuffled(reference, seed))
+            },
+            Intent::ReadIntent(Read::ReadFrom(ref reference)) => {
+                IntentResult::Playback(self.read_from(reference))
+            },
+            Intent::ReadIntent(ref read_intent) => {
+                let from = self.prev_index;
+
+                let result = match self.resolve_read_intent(read_intent) {
+                    Ok(node) => {
+                        // the options are read in their original order again, so letters name them as written
+                        self.presented.remove(&node.id);
+                        self.visit(&node);
+
+                        Ok(node.data.clone())
+                    },
+                    Err(e) => Err(e)
+                };
+
+                match read_intent {
+                    Read::FullQuestion(_) => IntentResult::FullQuestion(
+                        result.and_then(|data| match data {
+                            NodeData::Question(ref question) => Ok(FullQuestion::from(question)),
+                            _ => Err("The requested node is not a question".into())
+                        })
+                    ),
+                    Read::QuestionWithAnswer(_) => IntentResult::Answered(
+                        result.map(|data| {
+                            let answer = self.answer_of(self.nodes[self.prev_index].id).cloned();
+
+                            AnsweredQuestion { data, answered: answer.is_some(), answer }
+                        })
+                    ),
+                    _ => IntentResult::Read(result.map(|data| {
+                        let status = self.status_of(self.nodes[self.prev_index].id);
+                        let section = self.announce_section(self.prev_index);
+                        let distance = self.question_distance(from, self.prev_index);
+
+                        ReadOutcome { data, status, distance, section }
+                    }))
+                }
+            },
+            Intent::WriteIntent(ref write_intent) => {
+                let result = self.resolve_write_intent(write_intent);
+
+                IntentResult::Write(result)
+            },
+            Intent::Meta(ref meta) => {
+                match meta {
+                    MetaIntent::Marked => {
+                        // get the total marked and read them out
+                        return IntentResult::MetaData(MetaPayload {
+                            message: self.marked_message(),
+                            count: self.marked.len(),
+                            indices: self.marked_index.iter().cloned().collect()
+                        });
+                    },
+                    MetaIntent::Skipped => {
+                        return IntentResult::MetaData(MetaPayload {
+                            message: format!("You have skipped {} question", self.skipped.len()),
+                            count: self.skipped.len(),
+                            indices: self.indices_of(self.skipped.keys())
+                        });
+                    },
+                    MetaIntent::Notes(order) => {
+                        if self.notes.is_empty(){
+                            return IntentResult::Meta("You have not taken any notes".to_string());
+                        }
+
+                        IntentResult::Meta(self.notes_transcript(*order))
+                    },
+                    MetaIntent::Info => IntentResult::Meta(self.info()),
+                    MetaIntent::TimeRemaining(elapsed) => IntentResult::Meta(self.time_remaining(*elapsed)),
+                    MetaIntent::SlowestQuestions => IntentResult::Meta(self.slowest_questions()),
+                    MetaIntent::MarkBudget => IntentResult::Meta(self.mark_budget()),
+                    MetaIntent::Length => IntentResult::Meta(self.length())
+                }
+            },
+            Intent::Unknown(ref input) => {
+                IntentResult::Meta(format!("Sorry, I didn't understand \"{}\"", input))
+            }
+        }
+        
+    }
+
+    /// Keep the last `capacity` resolved intents for debugging, 0 turns tracing off
+    pub fn set_trace_capacity(&mut self, capacity: usize) {
+        self.trace.capacity = capacity;
+        self.trace.shrink();
+    }
+
+    /// The traced intents, oldest first
+    pub fn trace(&self) -> &VecDeque<TraceEntry> {
+        &self.trace.entries
+    }
+
+    /// Stop reference searches after scanning this many nodes, `None` searches the whole paper
+    pub fn set_max_search_nodes(&mut self, max_search_nodes: Option<usize>) {
+        self.max_search_nodes = max_search_nodes;
+    }
+
+    /// Lock the paper against writes, reads and meta queries still work
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    // the error every write returns while the paper is locked
+    fn read_only_refusal(&self) -> Option<WriteResult> {
+        if self.read_only {
+            return Some(WriteResult::Error(Borrowed("The paper is read-only")));
+        }
+
+        None
+    }
+
+    /// Allow skipping questions that already have an answer, refused by default
+    pub fn set_allow_skipping_answered(&mut self, allow: bool) {
+        self.allow_skipping_answered = allow;
+    }
+
+    /// Move on to the next question once an answer is recorded, off by default
+    pub fn set_advance_after_answer(&mut self, advance: bool) {
+        self.advance_after_answer = advance;
+    }
+
+    // move past an answered node when advancing is on, reporting the question moved to
+    fn after_answer(&mut self, index: usize, message: &str) -> WriteResult {
+        if !self.advance_after_answer {
+            return WriteResult::Success(message.to_string());
+        }
+
+        match (index + 1..self.len()).find(|next| self.nodes[*next].data.is_question()) {
+            Some(next) => {
+                self.visit_at(next);
+
+                WriteResult::Advanced(message.to_string(), self.nodes[next].data.clone())
+            },
+            None => WriteResult::Success(format!("{}, that was the last question", message))
+        }
+    }
+
+    // why a node cannot be skipped, if it cannot
+    fn skip_refusal(&self, id: NodeId) -> Option<WriteResult> {
+        if !self.allow_skipping_answered && self.answered.contains_key(&id) {
+            return Some(WriteResult::Error(Borrowed("You've already answered this question")));
+        }
+
         None
     }
-}
\ No newline at end of file
+
+    /// When enabled, a read that enters a new section reports the section's name
+    pub fn set_announce_sections(&mut self, announce: bool) {
+        self.announce_sections = announce;
+        self.announced_section = None;
+    }
+
+    // the section to announce before reading a node, once per section
+    fn announce_section(&mut self, index: usize) -> Option<String> {
+        if !self.announce_sections {
+            return None;
+        }
+
+        let (_, section) = self.section_of(index)?;
+
+        if self.announced_section.as_ref() == Some(&section) {
+            return None;
+        }
+
+        self.announced_section = Some(section.clone());
+
+        // reading the header itself already says the name
+        if self.nodes[index].data.is_section() {
+            return None;
+        }
+
+        Some(section)
+    }
+
+    /// When enabled, reference navigation passes over questions that have already been answered
+    pub fn set_skip_answered(&mut self, skip_answered: bool) {
+        self.skip_answered = skip_answered;
+    }
+
+    /// Answer a multiple choice question by the letter of one of its options
+    pub fn answer_by_letter(&mut self, reference: &Reference, letter: char) -> WriteResult {
+        if let Some(refusal) = self.read_only_refusal() {
+            return refusal;
+        }
+
+        let node = match self.with_all_questions(|paper| paper.resolve_question(reference)) {
+            Ok(node) => node,
+            Err(e) => return WriteResult::Error(e.message)
+        };
+
+        let options = match node.data {
+            NodeData::Question(ref question) => &question.options,
+            _ => return WriteResult::Error(Borrowed("The requested node is not a question"))
+        };
+
+        let letter = letter.to_ascii_uppercase();
+        let position = if letter.is_ascii_uppercase() {
+            (letter as u8 - b'A') as usize
+        }else{
+            options.len()
+        };
+
+        // a letter read out in shuffled order names the option shown at that position
+        let position = match self.presented.get(&node.id) {
+            Some(order) => order.get(position).cloned().unwrap_or(options.len()),
+            None => position
+        };
+
+        match options.get(position) {
+            Some(option) => {
+                self.record_answer(node.id, option.clone());
+
+                WriteResult::Success(format!("Option {} has been selected", letter))
+            },
+            None => WriteResult::Error(Cow::Owned(
+                format!("Option {} is not available, the question has {} options", letter, options.len())
+            ))
+        }
+    }
+
+    // read a question with its options shuffled, remembering the order they were read in
+    fn read_shuffled(&mut self, reference: &Reference, seed: u64) -> Result<FullQuestion, ReadError> {
+        let node = self.resolve_question(reference)?;
+
+        let question = match node.data {
+            NodeData::Question(ref question) => question,
+            _ => return Err("The requested node is not a question".into())
+        };
+
+        let order = builder::shuffled_order(question.options.len(), question.question_number, seed);
+        let mut full = FullQuestion::from(question);
+        full.options = full.options.map(|options| order.iter().map(|position| options[*position].clone()).collect());
+
+        self.presented.insert(node.id, order);
+        self.visit(&node);
+
+        Ok(full)
+    }
+
+    /// When enabled, an absolute reference to a node that does not match falls forward to the nearest match
+    pub fn set_nearest_fallback(&mut self, nearest_fallback: bool) {
+        self.nearest_fallback = nearest_fallback;
+    }
+
+    /// How far a `Current` reference is shifted before counting matches, 1 by default.
+    ///
+    /// The default reads the cursor's node for `Current(0)` and the next match for `Current(1)`.
+    /// With an offset of 0 the count is one based, `Current(1)` reads the cursor's node and `Current(2)` the next.
+    pub fn set_current_offset(&mut self, current_offset: usize) {
+        self.current_offset = current_offset;
+    }
+
+    /// Grade the recorded answer for a question, `None` if it is unanswered or has no key
+    pub fn grade(&self, id: NodeId) -> Option<bool> {
+        let answer = self.answer_of(id)?;
+
+        match self.nodes[self.index_of(id)?].data {
+            NodeData::Question(ref question) => question.grade(answer),
+            _ => None
+        }
+    }
+
+    /// The current answer for a node, the latest one given
+    pub fn answer_of(&self, id: NodeId) -> Option<&String> {
+        self.answered.get(&id).and_then(|history| history.last())
+    }
+
+    /// Every answer given for the node at an index, oldest first
+    pub fn answer_history(&self, index: usize) -> &[String] {
+        self.nodes.get(index)
+            .and_then(|node| self.answered.get(&node.id))
+            .map_or(&[], |history| history.as_slice())
+    }
+
+    // keep an answer after any given before it
+    fn record_answer(&mut self, id: NodeId, answer: String) {
+        self.answered.entry(id).or_default().push(answer);
+    }
+
+    // the marked count read out with a breakdown by reason
+    fn marked_message(&self) -> String {
+        let total_marked = self.marked.len();
+
+        if total_marked == 0 {
+            return "You have 0 marked questions".to_string();
+        }
+
+        let mut reasons: BTreeMap<MarkReason, usize> = BTreeMap::new();
+        for mark in self.marked.values() {
+            *reasons.entry(mark.reason).or_insert(0) += 1;
+        }
+
+        let breakdown: Vec<String> = reasons.iter()
+            .map(|(reason, count)| format!("{} {}", count, reason.label()))
+            .collect();
+
+        format!("You have {} marked questions: {}", total_marked, breakdown.join(", "))
+    }
+
+    // current positions of a set of nodes, in document order
+    fn indices_of<'a, I>(&self, ids: I) -> Vec<usize> where I: Iterator<Item = &'a NodeId> {
+        let mut indices: Vec<usize> = ids.filter_map(|id| self.index_of(*id)).collect();
+        indices.sort_unstable();
+
+        indices
+    }
+
+    /// The marks the whole paper is worth, `None` when no question says what it is worth
+    pub fn total_marks(&self) -> Option<u32> {
+        let marks: Vec<u32> = self.nodes.iter()
+            .filter_map(|node| match node.data {
+                NodeData::Question(ref question) => question.marks,
+                _ => None
+            })
+            .collect();
+
+        if marks.is_empty(){
+            None
+        }else{
+            Some(marks.iter().sum())
+        }
+    }
+
+    // the marks available on answered questions against the total, whether or not the answers are right
+    fn mark_budget(&self) -> String {
+        let total = match self.total_marks() {
+            Some(total) => total,
+            None => return "Marks are not available for this paper".to_string()
+        };
+
+        let secured: u32 = self.answered.keys()
+            .filter_map(|id| self.index_of(*id))
+            .filter_map(|index| match self.nodes[index].data {
+                NodeData::Question(ref question) => question.marks,
+                _ => None
+            })
+            .sum();
+
+        format!("You've secured answers worth {} of {} marks", secured, total)
+    }
+
+    /// Words across every question and question part, section headers and instructions left out
+    pub fn total_word_count(&self) -> usize {
+        self.nodes.iter()
+            .filter(|node| node.data.is_question() || node.data.is_sub_part())
+            .map(|node| node.data.word_count())
+            .sum()
+    }
+
+    // the word count read out, to the nearest hundred once it runs into thousands
+    fn length(&self) -> String {
+        let words = match self.total_word_count() {
+            words if words >= 1000 => (words + 50) / 100 * 100,
+            words => words
+        };
+
+        format!("This paper is about {} words", thousands(words))
+    }
+
+    /// Check how many questions have been marked for review
+    pub fn num_marked(&self) -> usize {
+        self.marked.len()
+    }
+
+    pub fn total_questions(&self) -> u32 {
+        self.total_questions
+    }
+
+    pub fn num_skipped(&self) -> usize {
+        self.skipped.len()
+    }
+
+    /// get the notes
+    pub fn notes(&self) -> &Vec<Note> {
+        &self.notes
+    }
+
+    /// Remove every note the predicate accepts, returning how many were removed. A read-only paper keeps its notes.
+    pub fn delete_notes_where<F: Fn(&Note) -> bool>(&mut self, f: F) -> usize {
+        if self.read_only_refusal().is_some() {
+            return 0;
+        }
+
+        let before = self.notes.len();
+
+        self.notes.retain(|note| !f(note));
+
+        before - self.notes.len()
+    }
+
+    /// Capture the navigation and write state so a batch of writes can be reverted
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            prev_index: self.prev_index,
+            max_reached: self.max_reached,
+            marked: self.marked.clone(),
+            skipped: self.skipped.clone(),
+            notes: self.notes.clone(),
+            answered: self.answered.clone(),
+            presented: self.presented.clone(),
+            time_spent: self.time_spent.clone()
+        }
+    }
+
+    /// Restore the state captured in a checkpoint
+    pub fn rollback(&mut self, checkpoint: Checkpoint) {
+        self.prev_index = checkpoint.prev_index;
+        self.max_reached = checkpoint.max_reached;
+        self.option_cursor = None;
+        self.marked = checkpoint.marked;
+        self.reindex_marks();
+        self.skipped = checkpoint.skipped;
+        self.notes = checkpoint.notes;
+        self.answered = checkpoint.answered;
+        self.presented = checkpoint.presented;
+        self.time_spent = checkpoint.time_spent;
+    }
+
+    /// Put the cursor back on a saved index, clamped to the paper, leaving marks, skips, notes and answers alone
+    pub fn restore_position(&mut self, index: usize) {
+        let index = index.min(self.len().saturating_sub(1));
+
+        self.update_previous(index);
+
+        // nothing has been read at the restored position yet, so its section is announced again
+        self.last_read = None;
+        self.announced_section = None;
+    }
+
+    /// Run a what if query against a copy of the paper, leaving this one untouched.
+    ///
+    /// The copy has no move callback, so listeners are not told about simulated moves.
+    pub fn simulate<F, R>(&self, f: F) -> R where F: FnOnce(&mut QuestionPaper) -> R {
+        let mut copy = self.clone();
+
+        f(&mut copy)
+    }
+
+    /// Reassign sequential question numbers in document order, after nodes have been edited
+    pub fn renumber(&mut self) {
+        let questions = self.nodes.iter_mut().filter_map(|node| match node.data {
+            NodeData::Question(ref mut question) => Some(question),
+            _ => None
+        });
+
+        for (number, question) in (1..).zip(questions) {
+            question.question_number = number;
+        }
+    }
+
+    /// Question numbers used by more than one question, in ascending order
+    pub fn duplicate_numbers(&self) -> Vec<u32> {
+        let mut counts: BTreeMap<u32, usize> = BTreeMap::new();
+
+        for number in self.nodes.iter().filter_map(|node| node.data.question_number()) {
+            *counts.entry(number).or_insert(0) += 1;
+        }
+
+        counts.into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(number, _)| number)
+            .collect()
+    }
+
+    /// Indices of the questions beyond the furthest point reached that have not been answered
+    pub fn unvisited(&self) -> Vec<usize> {
+        let answered = &self.answered;
+
+        let predicate = predicates::QuestionPredicate.and(|node: &NodeIndex| {
+            !answered.contains_key(&node.raw().id)
+        });
+
+        self.find_in_range(predicate, self.max_reached + 1..self.len())
+            .iter()
+            .map(|node| node.index())
+            .collect()
+    }
+
+    /// Render the paper as Markdown, with `##` section headers and numbered questions
+    pub fn to_markdown(&self) -> String {
+        let mut blocks = vec![];
+
+        if let Some(ref title) = self.metadata.title {
+            blocks.push(format!("# {}", title));
+        }
+
+        for node in &self.nodes {
+            match node.data {
+                NodeData::Section(ref section) => blocks.push(format!("## {}", section.section_name)),
+                NodeData::Question(ref question) => {
+                    let mut block = format!("{}. {}", question.question_number, question.question);
+
+                    for option in &question.options {
+                        block.push_str(&format!("\n   - {}", option));
+                    }
+
+                    blocks.push(block);
+                },
+                _ => ()
+            }
+        }
+
+        blocks.join("\n\n")
+    }
+
+    /// Snapshot the user's session on this paper
+    pub fn session(&self) -> SessionState {
+        SessionState {
+            prev_index: self.prev_index,
+            marked: self.marked.keys().cloned().collect(),
+            skipped: self.skipped.keys().cloned().collect(),
+            notes: self.notes.clone()
+        }
+    }
+
+    /// Pick up a saved session: the cursor, marks, skips and notes are replaced by the session's.
+    ///
+    /// Marks and skips on nodes that are not in the paper are dropped, a node still marked keeps its reason.
+    pub fn restore_session(&mut self, state: &SessionState) {
+        let mut marked = HashMap::new();
+        let mut skipped = HashMap::new();
+
+        for index in self.indices_of(state.marked.iter()) {
+            let node = &self.nodes[index];
+            let reason = self.marked.get(&node.id).map(|mark| mark.reason).unwrap_or_default();
+
+            marked.insert(node.id, Mark { data: node.data.clone(), reason });
+        }
+
+        for index in self.indices_of(state.skipped.iter()) {
+            let node = &self.nodes[index];
+            skipped.insert(node.id, node.data.clone());
+        }
+
+        self.marked = marked;
+        self.reindex_marks();
+        self.skipped = skipped;
+        self.notes = state.notes.clone();
+        self.restore_position(state.prev_index);
+    }
+
+    /// Notes taken on the paper as a whole rather than on a node
+    pub fn global_notes(&self) -> Vec<&Note> {
+        self.notes.iter().filter(|note| note.id == NodeId::GLOBAL).collect()
+    }
+
+    /// Notes whose node is gone from the paper or is no longer a question or part of one, general notes are never orphans
+    pub fn orphan_notes(&self) -> Vec<&Note> {
+        self.notes.iter()
+            .filter(|note| note.id != NodeId::GLOBAL)
+            .filter(|note| match self.index_of(note.id).map(|index| &self.nodes[index].data) {
+                Some(data) => !data.is_question() && !data.is_sub_part(),
+                None => true
+            })
+            .collect()
+    }
+
+    /// Every question that has notes, in paper order, alongside its notes
+    pub fn annotated_questions(&self) -> Vec<(NodeData, Vec<&Note>)> {
+        let mut by_index: BTreeMap<usize, Vec<&Note>> = BTreeMap::new();
+
+        for note in &self.notes {
+            if let Some(index) = self.index_of(note.id) {
+                if self.nodes[index].data.is_question() {
+                    by_index.entry(index).or_default().push(note);
+                }
+            }
+        }
+
+        by_index.into_iter()
+            .map(|(index, notes)| (self.nodes[index].data.clone(), notes))
+            .collect()
+    }
+
+    /// Every marked node in paper order alongside its notes, empty when it has none
+    pub fn marked_with_notes(&self) -> Vec<(NodeData, Vec<&Note>)> {
+        self.marked_index.iter()
+            .map(|index| {
+                let node = &self.nodes[*index];
+                let notes = self.notes.iter().filter(|note| note.id == node.id).collect();
+
+                (node.data.clone(), notes)
+            })
+            .collect()
+    }
+
+    /// Read out all notes, one per line, labelled with the node they were taken on.
+    ///
+    /// General notes follow under their own heading.
+    pub fn notes_transcript(&self, order: NoteOrder) -> String {
+        self.notes_transcript_with(&TranscriptOptions { order, ..TranscriptOptions::default() })
+    }
+
+    /// Read out all notes laid out as the options ask
+    pub fn notes_transcript_with(&self, options: &TranscriptOptions) -> String {
+        let (mut node_notes, mut global_notes): (Vec<&Note>, Vec<&Note>) = self.notes.iter()
+            .partition(|note| note.id != NodeId::GLOBAL);
+
+        if options.order == NoteOrder::RecentFirst {
+            node_notes.reverse();
+            global_notes.reverse();
+        }
+
+        let mut lines: Vec<String> = options.header.iter().cloned().collect();
+
+        lines.extend(node_notes.iter().map(|note| {
+            if options.include_numbers {
+                format!("{}: {}", self.note_label(note), note.note)
+            }else{
+                note.note.clone()
+            }
+        }));
+
+        if !global_notes.is_empty(){
+            lines.push("General".to_string());
+            lines.extend(global_notes.iter().map(|note| format!("- {}", note.note)));
+        }
+
+        lines.join(&options.separator)
+    }
+
+    /// The notes as JSON Lines, one object per note in the order they were taken
+    pub fn notes_as_jsonl(&self) -> String {
+        self.notes.iter()
+            .map(|note| {
+                let index = self.index_of(note.id);
+                let question_number = index.and_then(|index| self.nodes[index].data.question_number());
+
+                serde_json::json!({
+                    "index": index,
+                    "question_number": question_number,
+                    "text": note.note
+                }).to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // describe the node a note was taken on
+    fn note_label(&self, note: &Note) -> String {
+        let data = self.index_of(note.id).map(|index| &self.nodes[index].data);
+
+        match data {
+            Some(NodeData::Question(ref question)) => format!("Question {}", question.question_number),
+            Some(NodeData::Section(ref section)) => section.section_name.clone(),
+            _ => "Note".to_string()
+        }
+    }
+
+    // resolve the read intent in 
+    fn find_node(&mut self, reads: &[Read]) -> ReadResult {
+        let mut node = None;
+
+        for intent in reads {
+            node = Some(self.with_all_questions(|paper| paper.resolve_read_intent(intent)));
+        }
+
+        if node.is_none(){
+            return Err("Could not find the specified request".into());
+        }
+
+        node.unwrap()
+    }
+
+    // run a lookup for a write, which targets answered questions even when navigation skips them
+    fn with_all_questions<T, F: FnOnce(&mut Self) -> T>(&mut self, f: F) -> T {
+        let skip_answered = self.skip_answered;
+        self.skip_answered = false;
+
+        let found = f(self);
+        self.skip_answered = skip_answered;
+
+        found
+    }
+
+    // the index of the node a reference resolves to, without cloning it
+    fn locate<P: Predicate>(&mut self, predicate: P, prev: usize, skip: usize, reference: &Reference) -> Result<usize, ReadError> {
+        let skip_answered = self.skip_answered;
+        let answered = &self.answered;
+
+        let predicate = predicate.and(|node: &NodeIndex| {
+            !(skip_answered && answered.contains_key(&node.raw().id))
+        });
+
+        let mut finder = self.find(predicate, prev, skip);
+
+        if let Absolute(index) = reference {
+            // strict absolute references only accept the exact node
+            return match finder.next() {
+                Some(ref node) if self.nearest_fallback || node.index() == *index => Ok(node.index()),
+                _ => Err(format!("Node {} does not match the request", index).into())
+            };
+        }
+
+        // set the direction once, backward references walk the finder reversed
+        let forward = reference.is_forward();
+        let mut finder = if forward { finder } else { finder.rev() };
+
+        let error: ReadError = match finder.next() {
+            Some(node) => return Ok(node.index()),
+            None if finder.limit_reached() => return Err("Search limit reached".into()),
+            None if forward => "Could not find a next node".into(),
+            None => "Could not resolve a previous node".into()
+        };
+
+        Err(error.with_hint(finder.nearest(forward)))
+    }
+
+    // resolve a reference against a predicate to a node index
+    fn locate_reference<P: Predicate>(&mut self, reference: &Reference, predicate: P) -> Result<usize, ReadError> {
+        let (prev, skip) = self.anchor(reference);
+
+        self.locate(predicate, prev, skip, reference)
+    }
+
+    // resolve a question reference to a node index, End references use the cached positions
+    fn locate_question(&mut self, reference: &Reference) -> Result<usize, ReadError> {
+        if let End(skip) = reference {
+            if !self.skip_answered {
+                if let Some(index) = self.question_position_from_end(skip.unsigned_abs() as usize) {
+                    return Ok(index);
+                }
+            }
+        }
+
+        self.locate_reference(reference, predicates::QuestionPredicate)
+    }
+
+    /// Attach a note to a node, refusing notes with no text
+    fn attach_note(&mut self, id: NodeId, note: String) -> Result<(), Cow<'static, str>> {
+        check_note(&note)?;
+
+        self.notes.push(Note { note, id });
+
+        Ok(())
+    }
+
+}
+
+// every note taken must have some text
+fn check_note(note: &str) -> Result<(), Cow<'static, str>> {
+    if note.trim().is_empty(){
+        return Err(Borrowed("A note cannot be empty"));
+    }
+
+    Ok(())
+}
+
+// a count with commas between each group of three digits
+fn thousands(count: usize) -> String {
+    let digits = count.to_string();
+    let mut grouped = String::new();
+
+    for (position, digit) in digits.chars().enumerate() {
+        if position > 0 && (digits.len() - position).is_multiple_of(3) {
+            grouped.push(',');
+        }
+
+        grouped.push(digit);
+    }
+
+    grouped
+}
+
+// the least similarity at which a question word counts as a match for a search word
+const SEARCH_THRESHOLD: f32 = 0.75;
+
+// lowercase words, split on anything that is not a letter or digit
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+// normalized levenshtein similarity between two strings
+fn similarity(a: &str, b: &str) -> f32 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    // single row edit distance
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        let mut diagonal = row[0];
+        row[0] = i;
+
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let next = (row[j] + 1).min(row[j - 1] + 1).min(diagonal + cost);
+
+            diagonal = row[j];
+            row[j] = next;
+        }
+    }
+
+    1.0 - row[b.len()] as f32 / longest as f32
+}
+
+impl fmt::Display for QuestionPaper {
+    /// A one line summary of the paper state, without any node contents
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} questions, {} marked, {} skipped, {} notes, at node {}",
+            self.total_questions,
+            self.num_marked(),
+            self.num_skipped(),
+            self.notes.len(),
+            self.prev_index
+        )
+    }
+}
+
+impl Reader for QuestionPaper {
+    /// Resolves a read intent
+    fn resolve_read_intent(&mut self, read_intent: &Read) -> ReadResult {
+        match read_intent {
+            Read::Question(ref question) => self.resolve_question(question),
+            Read::Section(ref section) => self.resolve_section(section),
+            Read::FullQuestion(ref question) => self.resolve_question(question),
+            Read::QuestionContaining(ref term) => self.resolve_containing(term),
+            Read::NumberedQuestions(number) => {
+                let finder = self.find(predicates::QuestionNumberPredicate(*number), 0, 1);
+
+                self.find_next(finder)
+            },
+            Read::ReadFrom(ref reference) => self.resolve_question(reference),
+            Read::ShuffledQuestion(ref reference, _) => self.resolve_question(reference),
+            Read::MarksThreshold(percent) => self.resolve_marks_threshold(*percent),
+            Read::QuestionWithAnswer(ref reference) => self.resolve_question(reference),
+            Read::QuestionOfType(question_type, ref reference) => {
+                self.resolve_referece(reference, predicates::TypePredicate(*question_type))
+            },
+            Read::Any(ref reference) => {
+                // step from the exact node at the cursor, parts included
+                let (prev, skip) = self.anchor_from(reference, self.prev_index);
+                let predicate = |node: &NodeIndex| !matches!(node.data(), NodeData::Document);
+
+                self.resolve(predicate, prev, skip, reference)
+            },
+        }
+    }
+
+    /// Resolve the question where the cumulative marks cross a percentage of the total
+    fn resolve_marks_threshold(&mut self, percent: u32) -> ReadResult {
+        if percent > 100 {
+            return Err(format!("{} percent is more than the whole paper", percent).into());
+        }
+
+        let worth = |node: &Node| match node.data {
+            NodeData::Question(ref question) => question.marks.unwrap_or(0),
+            _ => 0
+        };
+
+        let total: u64 = self.nodes.iter().map(|node| u64::from(worth(node))).sum();
+
+        if total == 0 {
+            return Err("This paper does not say how many marks each question is worth".into());
+        }
+
+        let mut cumulative = 0;
+        let crossing = self.nodes.iter()
+            .filter(|node| node.data.is_question())
+            .find(|node| {
+                cumulative += u64::from(worth(node));
+                cumulative * 100 >= total * u64::from(percent)
+            });
+
+        crossing.cloned().ok_or_else(|| "Could not find a next node".into())
+    }
+
+    /// Resolve a question
+    fn resolve_question(&mut self, reference: &Reference) -> ReadResult {
+        let index = self.locate_question(reference)?;
+
+        Ok(self.nodes[index].clone())
+    }
+
+    /// Resolve a section
+    fn resolve_section(&mut self, reference: &Reference) -> ReadResult {
+        let predicate = predicates::SectionPredicate;
+
+        self.resolve_referece(reference, predicate)
+    }
+
+    /// Resolve the next question after the current position containing a keyword
+    fn resolve_containing(&mut self, term: &str) -> ReadResult {
+        let needle = term.to_lowercase();
+
+        let predicate = predicates::QuestionPredicate.and(|node: &NodeIndex| {
+            match node.data() {
+                NodeData::Question(ref question) => question.question.to_lowercase().contains(&needle),
+                _ => false
+            }
+        });
+
+        let finder = self.find(predicate, self.prev_index() + 1, 1);
+
+        self.find_next(finder)
+            .map_err(|_| format!("No question contains \"{}\"", term).into())
+    }
+
+    /// Resolve from a reference
+    fn resolve_referece<P: Predicate>(&mut self, reference: &Reference, predicate: P) -> ReadResult {
+
+        let (prev, skip) = self.anchor(reference);
+
+        self.resolve(predicate, prev, skip, reference)
+    }
+
+    fn resolve<P: Predicate>(&mut self, predicate: P, prev: usize, skip: usize, reference: &Reference) -> ReadResult {
+        let index = self.locate(predicate, prev, skip, reference)?;
+
+        Ok(self.nodes[index].clone())
+    }
+
+    /// Do a foward find
+    fn find_next<P: Predicate>(&self, mut finder: Find<P>) -> ReadResult {
+        if let Some(node) = finder.next(){
+            Ok(node.raw().clone())
+        }else{
+            Err("Could not find a next node".into())
+        }
+    }
+
+    /// Do a reverse find
+    fn find_back<P: Predicate>(&self, mut finder: Find<P>) -> ReadResult {
+        if let Some(node) = finder.next_back(){
+            Ok(node.raw().clone())
+        }else{
+            Err("Could not resolve a previous node".into())
+        }
+    }
+
+}
+
+impl Writer for QuestionPaper {
+    /// Resolve a write intent
+    fn resolve_write_intent(&mut self, write_intent: &Write) ->  WriteResult{
+        if let Some(refusal) = self.read_only_refusal() {
+            return refusal;
+        }
+
+        match write_intent {
+            Write::Mark(ref read_intents) => return self.mark_for_review(read_intents),
+            Write::MarkAs(ref read_intents, reason) => self.mark_as(read_intents, *reason),
+            Write::MarkNumber(number) => self.mark_number(*number),
+            Write::Skip(ref read_intents) => self.skip(read_intents),
+            Write::Note(ref read_intents, note) => self.note(read_intents, note.to_string()),
+            Write::NoteRange(from, to, note) => self.note_range(*from, *to, note.to_string()),
+            Write::Answer(ref read_intents, answer) => self.answer(read_intents, answer.to_string()),
+            Write::AnswerText(ref read_intents, answer) => self.answer_text(read_intents, answer.to_string()),
+            Write::GlobalNote(note) => self.global_note(note.to_string()),
+            Write::SkipWithNote(ref read_intents, note) => self.skip_with_note(read_intents, note.to_string())
+        }
+    }
+
+
+    // process a read intent and mark it for review
+    fn mark_for_review(&mut self, reads: &[Read]) -> WriteResult {
+        self.mark_as(reads, MarkReason::default())
+    }
+
+    fn mark_as(&mut self, reads: &[Read], reason: MarkReason) -> WriteResult {
+        if let Ok(node) = self.find_node(reads) {
+            self.insert_mark(node.id, Mark {
+                data: node.data.clone(),
+                reason
+            });
+
+            return WriteResult::Success(format!("Question has been marked {}", reason.label()));
+        }
+
+        WriteResult::Error(Borrowed("Could not mark the specified item for review. Please try again"))
+    }
+
+    fn mark_number(&mut self, number: u32) -> WriteResult {
+        let predicate = predicates::QuestionNumberPredicate(number);
+
+        let found = self.find(predicate, 0, 1)
+            .next()
+            .map(|node| (node.raw().id, node.data().clone()));
+
+        if let Some((id, data)) = found {
+            self.insert_mark(id, Mark {
+                data,
+                reason: MarkReason::default()
+            });
+
+            return WriteResult::Success(format!("Question {} has been marked for review", number));
+        }
+
+        WriteResult::Error(Cow::Owned(format!("There is no question {} to mark", number)))
+    }
+
+    fn skip(&mut self, reads: &[Read]) -> WriteResult {
+        if let Ok(node) = self.find_node(reads) {
+            if let Some(refusal) = self.skip_refusal(node.id) {
+                return refusal;
+            }
+
+            self.skipped.insert(node.id, node.data.clone());
+
+            return WriteResult::Success("Question has been skipped".to_string());
+        }
+        
+        return WriteResult::Error(Borrowed("Could not skip the specified item. Please try again"));        
+    }
+
+    /// Take a note on this node
+    fn note(&mut self, reads: &[Read], note: String) -> WriteResult {
+        if let Ok(node) = self.find_node(reads){
+            return match self.attach_note(node.id, note) {
+                Ok(()) => WriteResult::Success("A note has been taken".to_string()),
+                Err(message) => WriteResult::Error(message)
+            };
+        }
+
+        return WriteResult::Error(Borrowed("Could not take a note as requested"));
+    }
+
+    /// Take a note on each question numbered from `from` to `to` inclusive
+    fn note_range(&mut self, from: u32, to: u32, note: String) -> WriteResult {
+        if let Err(message) = check_note(&note) {
+            return WriteResult::Error(message);
+        }
+
+        let predicate = |node: &NodeIndex| {
+            matches!(node.data().question_number(), Some(number) if number >= from && number <= to)
+        };
+
+        let ids: Vec<NodeId> = self.find_in_range(predicate, 0..self.len())
+            .iter()
+            .map(|node| node.raw().id)
+            .collect();
+
+        if ids.is_empty(){
+            return WriteResult::Error(Cow::Owned(format!("There are no questions from {} to {}", from, to)));
+        }
+
+        for id in &ids {
+            self.notes.push(Note {
+                note: note.clone(),
+                id: *id
+            });
+        }
+
+        match ids.len() {
+            1 => WriteResult::Success("1 note has been taken".to_string()),
+            count => WriteResult::Success(format!("{} notes have been taken", count))
+        }
+    }
+
+    /// Take a note that is not tied to any node
+    fn global_note(&mut self, note: String) -> WriteResult {
+        match self.attach_note(NodeId::GLOBAL, note) {
+            Ok(()) => WriteResult::Success("A general note has been taken".to_string()),
+            Err(message) => WriteResult::Error(message)
+        }
+    }
+
+    /// Skip the node found and note why, undoing the skip if the note is refused
+    fn skip_with_note(&mut self, reads: &[Read], note: String) -> WriteResult {
+        let node = match self.find_node(reads) {
+            Ok(node) => node,
+            Err(_) => return WriteResult::Error(Borrowed("Could not skip the specified item. Please try again"))
+        };
+
+        if let Some(refusal) = self.skip_refusal(node.id) {
+            return refusal;
+        }
+
+        let previous = self.skipped.insert(node.id, node.data.clone());
+
+        if let Err(message) = self.attach_note(node.id, note) {
+            match previous {
+                Some(data) => self.skipped.insert(node.id, data),
+                None => self.skipped.remove(&node.id)
+            };
+
+            return WriteResult::Error(message);
+        }
+
+        WriteResult::Success("Question has been skipped with a note".to_string())
+    }
+
+    /// Record an answer for the node found
+    fn answer(&mut self, reads: &[Read], answer: String) -> WriteResult {
+        if let Ok(node) = self.find_node(reads){
+            self.record_answer(node.id, answer);
+
+            return self.after_answer(node.index, "Your answer has been recorded");
+        }
+
+        WriteResult::Error(Borrowed("Could not record the answer as requested"))
+        
+    }
+
+    /// Record a free text answer, multiple choice questions are answered by letter instead
+    fn answer_text(&mut self, reads: &[Read], answer: String) -> WriteResult {
+        let node = match self.find_node(reads) {
+            Ok(node) => node,
+            Err(_) => return WriteResult::Error(Borrowed("Could not record the answer as requested"))
+        };
+
+        let has_key = match node.data {
+            NodeData::Question(ref question) if question.options.is_empty() => question.key.is_some(),
+            NodeData::Question(_) => return WriteResult::Error(Borrowed("This question has options, answer it with a letter")),
+            _ => return WriteResult::Error(Borrowed("The requested node is not a question"))
+        };
+
+        self.record_answer(node.id, answer);
+
+        if has_key {
+            self.after_answer(node.index, "Your answer has been recorded")
+        }else{
+            self.after_answer(node.index, "Your answer has been recorded and requires manual grading")
+        }
+    }
+}
+
+
+pub struct Find<'a, P:Predicate> {
+    predicate: P,
+    next: usize,
+    question_paper: &'a QuestionPaper,
+    skip: usize,
+    reversed: bool,
+    skip_sections: bool,
+    scanned: usize,
+    max_nodes: Option<usize>
+}
+
+impl<'a, P: Predicate> Find<'a, P> {
+    /// Consume the search in the opposite direction, from the same anchor and with the same skip
+    pub fn rev(mut self) -> Self {
+        self.reversed = !self.reversed;
+        self
+    }
+
+    /// Pass over section headers without offering them to the predicate
+    pub fn skip_sections(mut self, skip_sections: bool) -> Self {
+        self.skip_sections = skip_sections;
+        self
+    }
+
+    // the match furthest along in the direction searched, to suggest when the search runs out
+    fn nearest(&self, forward: bool) -> Option<usize> {
+        let mut indices = 0..self.question_paper.len();
+        let matches = |index: &usize| {
+            self.question_paper.nth(*index).is_some_and(|node| self.considers(&node))
+        };
+
+        if forward { indices.rev().find(matches) } else { indices.find(matches) }
+    }
+
+    /// Whether the search stopped because it scanned as many nodes as it is allowed to
+    pub fn limit_reached(&self) -> bool {
+        self.max_nodes.is_some_and(|max| self.scanned >= max)
+    }
+
+    // count a node as scanned, false once the cap is spent
+    fn scan(&mut self) -> bool {
+        if self.limit_reached(){
+            return false;
+        }
+
+        self.scanned += 1;
+        true
+    }
+
+    // whether a node takes part in the search
+    fn considers(&self, node: &NodeIndex) -> bool {
+        !(self.skip_sections && node.data().is_section()) && self.predicate.matches(node)
+    }
+
+    fn step_forward(&mut self) -> Option<NodeIndex<'a>> {
+        while self.next < self.question_paper.len() && self.scan(){
+            let node = self.question_paper.nth(self.next)?;
+
+            self.next = self.next.saturating_add(1);
+
+            if self.considers(&node){
+                if self.skip >= 1 {  
+                   
+                    self.skip = self.skip.saturating_sub(1);
+                }else{
+                    return Some(node);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn step_back(&mut self) -> Option<NodeIndex<'a>> {
+        // an anchor past the end searches back from the last node
+        self.next = self.next.min(self.question_paper.len().saturating_sub(1));
+
+        while self.next > 0 && self.scan() {
+            let node = self.question_paper.nth(self.next)?;
+
+            self.next = self.next.saturating_sub(1);
+
+            if self.considers(&node) {
+                if self.skip >= 1 {
+                    self.skip = self.skip.saturating_sub(1);
+                }else{
+                    return Some(node);
+                }
+               
+            }
+        }
+
+        None
+    }
+}
+
+impl <'a, P: Predicate> Iterator for Find<'a, P> {
+    type Item = NodeIndex<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.reversed {
+            self.step_back()
+        }else{
+            self.step_forward()
+        }
+    }
+}
+
+
+impl<'a, P: Predicate> DoubleEndedIterator for Find<'a, P> {
+    fn next_back(&mut self) -> Option<NodeIndex<'a>> {
+        if self.reversed {
+            self.step_forward()
+        }else{
+            self.step_back()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Sink, Tokenizer};
+    use crate::Builder;
+    use builder::QuestionData;
+    use std::sync::mpsc;
+
+    const PAPER: &str = r#"<?xml version="1.0"?>
+<root>
+<instructions>Answer all questions</instructions>
+<meta_data><title>End of Term Exam</title><subject>Science</subject><duration>90</duration></meta_data>
+<SECTION_A>
+<section_number>Section A</section_number>
+<item><question>Which gas do plants absorb?</question><option>Oxygen</option><option>Carbon dioxide</option><option>Nitrogen</option><answer>B</answer></item>
+<item><question>Name the parts of a cell.</question></item>
+<item><question>Define osmosis.</question></item>
+</SECTION_A>
+<SECTION_B>
+<section_number>Section B</section_number>
+<item><question>Explain the water cycle.</question></item>
+<item><question>Describe the structure of an atom.</question></item>
+</SECTION_B>
+</root>"#;
+
+    // tokenize and build a question paper from an xml string
+    fn paper(xml: &str) -> QuestionPaper {
+        build(xml, QPaperBuilder::new())
+    }
+
+    fn build(xml: &str, mut builder: QPaperBuilder) -> QuestionPaper {
+        let (tx, rx) = mpsc::channel();
+
+        Tokenizer::tokenize(vec![xml.to_string()], Sink::new(tx));
+
+        for tag in rx {
+            builder.process_tag(tag);
+        }
+
+        builder.end()
+    }
+
+    // the fixture with a `<tag>` after each listed question, carrying the value paired with it
+    fn tagged_paper(tag: &str, values: &[(&str, &str)]) -> QuestionPaper {
+        let mut xml = PAPER.to_string();
+
+        for (text, value) in values {
+            let question = format!("<question>{}</question>", text);
+            xml = xml.replace(&question, &format!("{0}<{1}>{2}</{1}>", question, tag, value));
+        }
+
+        paper(&xml)
+    }
+
+    // build a paper with the given number of questions in each section
+    fn sections_paper(counts: &[usize]) -> QuestionPaper {
+        let mut xml = String::from("<?xml version=\"1.0\"?>\n<root>\n");
+        let mut number = 0;
+
+        for (i, count) in counts.iter().enumerate() {
+            let letter = (b'A' + i as u8) as char;
+            xml.push_str(&format!("<SECTION_{0}>\n<section_number>Section {0}</section_number>\n", letter));
+
+            for _ in 0..*count {
+                number += 1;
+                xml.push_str(&format!("<item><question>Question text {}</question></item>\n", number));
+            }
+
+            xml.push_str(&format!("</SECTION_{}>\n", letter));
+        }
+
+        xml.push_str("</root>");
+
+        paper(&xml)
+    }
+
+    fn question(reference: Reference) -> Vec<Read> {
+        vec![Read::Question(reference)]
+    }
+
+    #[test]
+    fn marks_survive_reordering(){
+        let mut qp = paper(PAPER);
+
+        let result = qp.resolve_write_intent(&Write::Mark(question(Start(1))));
+        assert_eq!(result, WriteResult::Success("Question has been marked for review".to_string()));
+
+        let id = *qp.marked.keys().next().unwrap();
+        assert_eq!(qp.index_of(id), Some(2));
+
+        // reorder the nodes, the mark should still point at the same question
+        qp.nodes.reverse();
+
+        let index = qp.index_of(id).unwrap();
+        assert_eq!(index, qp.len() - 3);
+        assert_eq!(&qp.nodes[index].data, &qp.marked[&id].data);
+    }
+
+    #[test]
+    fn full_question_includes_options(){
+        let mut qp = paper(PAPER);
+
+        match qp.resolve_intent(Intent::ReadIntent(Read::FullQuestion(Start(1)))) {
+            IntentResult::FullQuestion(Ok(full)) => {
+                assert_eq!(full.question_number, 1);
+                assert_eq!(full.stem, "Which gas do plants absorb?");
+                assert_eq!(
+                    full.options,
+                    Some(vec!["Oxygen".to_string(), "Carbon dioxide".to_string(), "Nitrogen".to_string()])
+                );
+            },
+            other => panic!("unexpected result {:?}", other)
+        }
+
+        match qp.resolve_intent(Intent::ReadIntent(Read::FullQuestion(Start(2)))) {
+            IntentResult::FullQuestion(Ok(full)) => {
+                assert_eq!(full.question_number, 2);
+                assert_eq!(full.stem, "Name the parts of a cell.");
+                assert_eq!(full.options, None);
+            },
+            other => panic!("unexpected result {:?}", other)
+        }
+    }
+
+    #[test]
+    fn display_summarizes_state(){
+        let mut qp = paper(PAPER);
+        assert_eq!(qp.to_string(), "5 questions, 0 marked, 0 skipped, 0 notes, at node 0");
+
+        qp.resolve_intent(Intent::ReadIntent(Read::Question(Start(2))));
+        qp.resolve_write_intent(&Write::Mark(question(Current(0))));
+        qp.resolve_write_intent(&Write::Skip(question(Current(1))));
+        qp.resolve_write_intent(&Write::Note(question(Current(0)), "check units".to_string()));
+
+        assert_eq!(qp.to_string(), "5 questions, 1 marked, 1 skipped, 1 notes, at node 3");
+    }
+
+    #[test]
+    fn mark_question_by_number(){
+        let mut qp = paper(PAPER);
+
+        let result = qp.resolve_write_intent(&Write::MarkNumber(4));
+        assert_eq!(result, WriteResult::Success("Question 4 has been marked for review".to_string()));
+
+        let id = *qp.marked.keys().next().unwrap();
+        assert_eq!(qp.nodes[qp.index_of(id).unwrap()].data.question_number(), Some(4));
+    }
+
+    #[test]
+    fn mark_missing_question_number(){
+        let mut qp = paper(PAPER);
+
+        let result = qp.resolve_write_intent(&Write::MarkNumber(12));
+        assert_eq!(result, WriteResult::Error(Borrowed("There is no question 12 to mark")));
+        assert_eq!(qp.num_marked(), 0);
+    }
+
+    #[test]
+    fn notes_in_reverse_order(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_write_intent(&Write::Note(question(Start(1)), "revise gases".to_string()));
+        qp.resolve_write_intent(&Write::Note(question(Start(3)), "draw a diagram".to_string()));
+
+        assert_eq!(
+            qp.notes_transcript(NoteOrder::default()),
+            "Question 1: revise gases\nQuestion 3: draw a diagram"
+        );
+
+        match qp.resolve_intent(Intent::Meta(MetaIntent::Notes(NoteOrder::RecentFirst))) {
+            IntentResult::Meta(transcript) => {
+                assert_eq!(transcript, "Question 3: draw a diagram\nQuestion 1: revise gases");
+            },
+            other => panic!("unexpected result {:?}", other)
+        }
+    }
+
+    #[test]
+    fn rollback_restores_checkpoint(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_write_intent(&Write::Mark(question(Start(1))));
+        let checkpoint = qp.checkpoint();
+
+        qp.resolve_intent(Intent::ReadIntent(Read::Question(Start(4))));
+        qp.resolve_write_intent(&Write::Mark(question(Start(2))));
+        qp.resolve_write_intent(&Write::Skip(question(Start(3))));
+        qp.resolve_write_intent(&Write::Note(question(Start(3)), "come back".to_string()));
+        assert_eq!(qp.to_string(), "5 questions, 2 marked, 1 skipped, 1 notes, at node 6");
+
+        qp.rollback(checkpoint);
+
+        assert_eq!(qp.to_string(), "5 questions, 1 marked, 0 skipped, 0 notes, at node 0");
+        assert_eq!(qp.marked.keys().collect::<Vec<_>>(), vec![&NodeId(2)]);
+    }
+
+    #[test]
+    fn find_question_containing_keyword(){
+        let mut qp = paper(PAPER);
+
+        match qp.resolve_intent(Intent::ReadIntent(Read::QuestionContaining("WATER".to_string()))) {
+            IntentResult::Read(Ok(ReadOutcome { data: NodeData::Question(question), .. })) => {
+                assert_eq!(question.question, "Explain the water cycle.");
+            },
+            other => panic!("unexpected result {:?}", other)
+        }
+        assert_eq!(qp.prev_index(), 6);
+
+        // the search only runs forward from the current question
+        match qp.resolve_intent(Intent::ReadIntent(Read::QuestionContaining("cell".to_string()))) {
+            IntentResult::Read(Err(e)) => assert_eq!(e, "No question contains \"cell\""),
+            other => panic!("unexpected result {:?}", other)
+        }
+    }
+
+    #[test]
+    fn find_missing_keyword(){
+        let mut qp = paper(PAPER);
+
+        match qp.resolve_intent(Intent::ReadIntent(Read::QuestionContaining("gravity".to_string()))) {
+            IntentResult::Read(Err(e)) => assert_eq!(e, "No question contains \"gravity\""),
+            other => panic!("unexpected result {:?}", other)
+        }
+        assert_eq!(qp.prev_index(), 0);
+    }
+
+    #[test]
+    fn remaining_from_section_start(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_intent(Intent::ReadIntent(Read::Section(Start(1))));
+        assert_eq!(qp.remaining_in_section(), 3);
+
+        qp.resolve_intent(Intent::ReadIntent(Read::Section(Start(2))));
+        assert_eq!(qp.remaining_in_section(), 2);
+    }
+
+    #[test]
+    fn remaining_from_middle_of_section(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_intent(Intent::ReadIntent(Read::Question(Start(2))));
+        assert_eq!(qp.remaining_in_section(), 1);
+
+        qp.resolve_intent(Intent::ReadIntent(Read::Question(Start(5))));
+        assert_eq!(qp.remaining_in_section(), 0);
+    }
+
+    fn read(qp: &mut QuestionPaper, read: Read) -> NodeData {
+        read_outcome(qp, read).data
+    }
+
+    fn read_outcome(qp: &mut QuestionPaper, read: Read) -> ReadOutcome {
+        match qp.resolve_intent(Intent::ReadIntent(read)) {
+            IntentResult::Read(Ok(outcome)) => outcome,
+            other => panic!("unexpected result {:?}", other)
+        }
+    }
+
+    #[test]
+    fn navigation_skips_answered_questions(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_write_intent(&Write::Answer(question(Start(2)), "nucleus".to_string()));
+        qp.resolve_write_intent(&Write::Answer(question(Start(3)), "diffusion of water".to_string()));
+        qp.set_skip_answered(true);
+
+        read(&mut qp, Read::Question(Start(1)));
+        assert_eq!(read(&mut qp, Read::Question(Current(1))).question_number(), Some(4));
+        assert_eq!(read(&mut qp, Read::Question(Current(-1))).question_number(), Some(1));
+
+        // writes still reach answered questions, so an answer can be changed
+        qp.resolve_write_intent(&Write::Answer(question(Start(2)), "cell wall".to_string()));
+        assert_eq!(qp.answer_of(qp.nodes[3].id), Some(&"cell wall".to_string()));
+        assert_eq!(qp.answer_history(3), ["nucleus", "cell wall"]);
+        assert_eq!(qp.answer_history(4), ["diffusion of water"]);
+    }
+
+    #[test]
+    fn navigation_visits_everything_by_default(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_write_intent(&Write::Answer(question(Start(2)), "nucleus".to_string()));
+        assert_eq!(qp.answer_of(NodeId(3)), Some(&"nucleus".to_string()));
+
+        read(&mut qp, Read::Question(Start(1)));
+        assert_eq!(read(&mut qp, Read::Question(Current(1))).question_number(), Some(2));
+    }
+
+    #[test]
+    fn match_approximate_section_name(){
+        let qp = paper(PAPER);
+
+        let candidates = qp.match_sections("sektion b");
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].0, "Section B");
+        assert!(candidates[0].1 > candidates[1].1);
+        assert!(candidates.iter().all(|(_, score)| *score >= 0.0 && *score <= 1.0));
+
+        assert_eq!(qp.match_sections("Section A")[0], ("Section A".to_string(), 1.0));
+    }
+
+    #[test]
+    fn note_a_range_of_questions(){
+        let mut qp = sections_paper(&[8, 6]);
+
+        let result = qp.resolve_write_intent(&Write::NoteRange(10, 12, "all about vectors".to_string()));
+        assert_eq!(result, WriteResult::Success("3 notes have been taken".to_string()));
+
+        let noted: Vec<Option<u32>> = qp.notes().iter()
+            .map(|note| qp.nodes[qp.index_of(note.id).unwrap()].data.question_number())
+            .collect();
+        assert_eq!(noted, vec![Some(10), Some(11), Some(12)]);
+        assert!(qp.notes().iter().all(|note| note.note == "all about vectors"));
+
+        let result = qp.resolve_write_intent(&Write::NoteRange(20, 25, "missing".to_string()));
+        assert_eq!(result, WriteResult::Error(Borrowed("There are no questions from 20 to 25")));
+
+        let result = qp.resolve_write_intent(&Write::NoteRange(4, 4, "scalars".to_string()));
+        assert_eq!(result, WriteResult::Success("1 note has been taken".to_string()));
+    }
+
+    #[test]
+    fn blank_notes_are_refused_everywhere(){
+        let mut qp = paper(PAPER);
+        let refused = WriteResult::Error(Borrowed("A note cannot be empty"));
+
+        assert_eq!(qp.resolve_write_intent(&Write::Note(question(Start(1)), " \t".to_string())), refused);
+        assert_eq!(qp.resolve_write_intent(&Write::NoteRange(1, 3, "".to_string())), refused);
+        assert_eq!(qp.resolve_write_intent(&Write::GlobalNote("  ".to_string())), refused);
+
+        read(&mut qp, Read::Question(Start(2)));
+        assert_eq!(qp.note_current("\n".to_string()), refused);
+        assert!(qp.notes.is_empty());
+
+        let result = qp.resolve_write_intent(&Write::Note(question(Start(1)), "revise gases".to_string()));
+        assert_eq!(result, WriteResult::Success("A note has been taken".to_string()));
+    }
+
+    #[test]
+    fn last_read_after_successful_read(){
+        let mut qp = paper(PAPER);
+        assert!(qp.last_read().is_none());
+
+        read(&mut qp, Read::Question(Start(3)));
+
+        let last = qp.last_read().unwrap();
+        assert_eq!(last.index(), 4);
+        assert_eq!(last.data().question_number(), Some(3));
+    }
+
+    #[test]
+    fn last_read_ignores_failed_reads(){
+        let mut qp = paper(PAPER);
+
+        read(&mut qp, Read::Question(Start(2)));
+
+        match qp.resolve_intent(Intent::ReadIntent(Read::Question(Start(9)))) {
+            IntentResult::Read(Err(_)) => (),
+            other => panic!("unexpected result {:?}", other)
+        }
+
+        assert_eq!(qp.last_read().unwrap().data().question_number(), Some(2));
+    }
+
+    #[test]
+    fn reference_constructors(){
+        let mut qp = paper(PAPER);
+
+        assert_eq!(read(&mut qp, Read::Question(Reference::nth_from_start(1))).question_number(), Some(1));
+        assert_eq!(read(&mut qp, Read::Question(Reference::nth_from_start(3))).question_number(), Some(3));
+        assert_eq!(read(&mut qp, Read::Question(Reference::next())).question_number(), Some(4));
+        assert_eq!(read(&mut qp, Read::Question(Reference::previous())).question_number(), Some(3));
+        assert_eq!(read(&mut qp, Read::Question(Reference::nth_from_end(1))).question_number(), Some(5));
+        assert_eq!(read(&mut qp, Read::Question(Reference::nth_from_end(2))).question_number(), Some(4));
+    }
+
+    #[test]
+    fn on_move_fires_on_cursor_changes(){
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let mut qp = paper(PAPER);
+        let moves = Arc::new(AtomicUsize::new(0));
+
+        let counter = moves.clone();
+        qp.set_on_move(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        read(&mut qp, Read::Question(Start(1)));
+        read(&mut qp, Read::Question(Current(0)));
+        read(&mut qp, Read::Question(Current(1)));
+        read(&mut qp, Read::Question(Start(2)));
+
+        assert_eq!(moves.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn previous_question_from_mid_paper(){
+        let mut qp = paper(PAPER);
+
+        read(&mut qp, Read::Question(Start(4)));
+
+        let node = qp.previous_question().unwrap();
+        assert_eq!(node.data.question_number(), Some(3));
+        assert_eq!(qp.prev_index(), node.index);
+        assert_eq!(qp.previous_question().unwrap().data.question_number(), Some(2));
+    }
+
+    #[test]
+    fn previous_question_at_start(){
+        let mut qp = paper(PAPER);
+
+        assert_eq!(qp.previous_question().unwrap_err(), "You are already at the first question");
+
+        read(&mut qp, Read::Question(Start(1)));
+        assert_eq!(qp.previous_question().unwrap_err(), "You are already at the first question");
+        assert_eq!(qp.prev_index(), 2);
+    }
+
+    #[test]
+    fn answer_with_valid_letter(){
+        let mut qp = paper(PAPER);
+
+        let result = qp.answer_by_letter(&Start(1), 'b');
+        assert_eq!(result, WriteResult::Success("Option B has been selected".to_string()));
+        assert_eq!(qp.answer_of(NodeId(2)), Some(&"Carbon dioxide".to_string()));
+    }
+
+    #[test]
+    fn answer_with_out_of_range_letter(){
+        let mut qp = paper(PAPER);
+
+        let result = qp.answer_by_letter(&Start(1), 'D');
+        assert_eq!(result, WriteResult::Error(Borrowed("Option D is not available, the question has 3 options")));
+
+        let result = qp.answer_by_letter(&Start(2), 'A');
+        assert_eq!(result, WriteResult::Error(Borrowed("Option A is not available, the question has 0 options")));
+        assert!(qp.answered.is_empty());
+    }
+
+    #[test]
+    fn count_forward_from_mid_paper(){
+        let mut qp = paper(PAPER);
+
+        read(&mut qp, Read::Question(Start(2)));
+
+        assert_eq!(qp.count_from(predicates::QuestionPredicate, &Reference::next()), 3);
+        assert_eq!(qp.count_from(predicates::SectionPredicate, &Reference::next()), 0);
+        assert_eq!(qp.count_from(predicates::SectionPredicate, &Current(0)), 1);
+    }
+
+    #[test]
+    fn count_backward_from_end(){
+        let qp = paper(PAPER);
+
+        assert_eq!(qp.count_from(predicates::QuestionPredicate, &Reference::nth_from_end(1)), 5);
+        assert_eq!(qp.count_from(predicates::QuestionPredicate, &Reference::nth_from_end(2)), 4);
+    }
+
+    #[test]
+    fn unknown_intent_is_echoed(){
+        let mut qp = paper(PAPER);
+
+        match qp.resolve_intent(Intent::Unknown("play some music".to_string())) {
+            IntentResult::Meta(message) => assert_eq!(message, "Sorry, I didn't understand \"play some music\""),
+            other => panic!("unexpected result {:?}", other)
+        }
+        assert_eq!(qp.prev_index(), 0);
+    }
+
+    fn indices(nodes: Vec<NodeIndex>) -> Vec<usize> {
+        nodes.iter().map(|node| node.index()).collect()
+    }
+
+    #[test]
+    fn reversed_find_matches_backward_find(){
+        let qp = paper(PAPER);
+        let last = qp.last_index().unwrap();
+
+        for skip in 1..4 {
+            let mut backward = qp.find(predicates::QuestionPredicate, last, skip);
+            let expected: Vec<NodeIndex> = std::iter::from_fn(|| backward.next_back()).collect();
+
+            let reversed: Vec<NodeIndex> = qp.find(predicates::QuestionPredicate, last, skip).rev().collect();
+
+            assert_eq!(indices(reversed), indices(expected));
+        }
+
+        let reversed = qp.find(predicates::QuestionPredicate, last, 2).rev().collect();
+        assert_eq!(indices(reversed), vec![6, 4, 3, 2]);
+    }
+
+    #[test]
+    fn reversing_twice_restores_direction(){
+        let qp = paper(PAPER);
+
+        let forward = qp.find(predicates::QuestionPredicate, 0, 1).collect();
+        let twice = qp.find(predicates::QuestionPredicate, 0, 1).rev().rev().collect();
+
+        assert_eq!(indices(twice), indices(forward));
+    }
+
+    #[test]
+    fn progress_per_section(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_write_intent(&Write::Answer(question(Start(1)), "Carbon dioxide".to_string()));
+        qp.resolve_write_intent(&Write::Answer(question(Start(3)), "diffusion of water".to_string()));
+        qp.resolve_write_intent(&Write::Answer(question(Start(5)), "protons and electrons".to_string()));
+
+        assert_eq!(qp.section_progress(), vec![
+            ("Section A".to_string(), 2, 3),
+            ("Section B".to_string(), 1, 2)
+        ]);
+    }
+
+    #[test]
+    fn renumber_after_inserting_a_question(){
+        let mut qp = paper(PAPER);
+
+        let mut inserted = qp.nodes[2].clone();
+        inserted.id = NodeId(qp.len());
+        inserted.data = NodeData::Question(QuestionData {
+            question: "State Newton's first law.".to_string(),
+            ..QuestionData::default()
+        });
+        qp.edit_nodes(|nodes| nodes.insert(3, inserted));
+
+        qp.renumber();
+
+        let numbers: Vec<u32> = qp.nodes.iter().filter_map(|node| node.data.question_number()).collect();
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(qp.nodes[3].data.question_number(), Some(2));
+
+        let sections: Vec<&str> = qp.nodes.iter().filter_map(|node| node.data.section_name()).collect();
+        assert_eq!(sections, vec!["Section A", "Section B"]);
+    }
+
+    #[test]
+    fn detect_duplicate_numbers(){
+        let mut qp = paper(PAPER);
+        assert!(qp.duplicate_numbers().is_empty());
+
+        if let NodeData::Question(ref mut question) = qp.nodes[6].data {
+            question.question_number = 2;
+        }
+
+        assert_eq!(qp.duplicate_numbers(), vec![2]);
+    }
+
+    fn meta(qp: &mut QuestionPaper, meta: MetaIntent) -> String {
+        match qp.resolve_intent(Intent::Meta(meta)) {
+            IntentResult::Meta(message) => message,
+            IntentResult::MetaData(payload) => payload.message,
+            other => panic!("unexpected result {:?}", other)
+        }
+    }
+
+    #[test]
+    fn info_reads_metadata(){
+        let mut qp = paper(PAPER);
+
+        assert_eq!(qp.metadata().duration_minutes, Some(90));
+        assert_eq!(meta(&mut qp, MetaIntent::Info), "End of Term Exam. Subject: Science. Duration: 90 minutes");
+    }
+
+    #[test]
+    fn info_omits_missing_metadata(){
+        let mut qp = sections_paper(&[2]);
+        assert_eq!(meta(&mut qp, MetaIntent::Info), "No information is available for this paper");
+
+        let mut qp = sections_paper(&[2]).with_metadata(Metadata {
+            subject: Some("Physics".to_string()),
+            ..Metadata::default()
+        });
+        assert_eq!(meta(&mut qp, MetaIntent::Info), "Subject: Physics");
+    }
+
+    #[test]
+    fn absolute_reference_is_strict_by_default(){
+        let mut qp = paper(PAPER);
+
+        assert_eq!(read(&mut qp, Read::Question(Absolute(3))).question_number(), Some(2));
+
+        match qp.resolve_intent(Intent::ReadIntent(Read::Question(Absolute(5)))) {
+            IntentResult::Read(Err(e)) => assert_eq!(e, "Node 5 does not match the request"),
+            other => panic!("unexpected result {:?}", other)
+        }
+    }
+
+    #[test]
+    fn absolute_reference_falls_forward_to_nearest_match(){
+        let mut qp = paper(PAPER);
+        qp.set_nearest_fallback(true);
+
+        // node 5 is the section B header
+        let data = read(&mut qp, Read::Question(Absolute(5)));
+        assert_eq!(data.question_number(), Some(4));
+        assert_eq!(qp.prev_index(), 6);
+    }
+
+    fn shuffled(seed: u64) -> QuestionPaper {
+        let mut builder = QPaperBuilder::new();
+        builder.shuffle_options(seed);
+
+        build(PAPER, builder)
+    }
+
+    fn options(qp: &QuestionPaper, index: usize) -> Vec<String> {
+        match qp.nodes[index].data {
+            NodeData::Question(ref question) => question.options.clone(),
+            _ => vec![]
+        }
+    }
+
+    #[test]
+    fn shuffle_is_stable_for_a_seed(){
+        let first = shuffled(3);
+        let second = shuffled(3);
+
+        assert_eq!(options(&first, 2), options(&second, 2));
+        assert_eq!(options(&first, 2), vec!["Nitrogen", "Oxygen", "Carbon dioxide"]);
+
+        let mut sorted = options(&first, 2);
+        sorted.sort();
+        assert_eq!(sorted, vec!["Carbon dioxide", "Nitrogen", "Oxygen"]);
+
+        // unshuffled papers keep the original order
+        assert_eq!(options(&paper(PAPER), 2), vec!["Oxygen", "Carbon dioxide", "Nitrogen"]);
+    }
+
+    #[test]
+    fn grading_maps_through_the_shuffle(){
+        let mut qp = shuffled(3);
+
+        // carbon dioxide is now option C, the key says B in the original order
+        qp.answer_by_letter(&Start(1), 'C');
+        assert_eq!(qp.grade(NodeId(2)), Some(true));
+
+        qp.answer_by_letter(&Start(1), 'B');
+        assert_eq!(qp.grade(NodeId(2)), Some(false));
+
+        let mut qp = paper(PAPER);
+        qp.answer_by_letter(&Start(1), 'B');
+        assert_eq!(qp.grade(NodeId(2)), Some(true));
+    }
+
+    #[test]
+    fn mark_with_reasons(){
+        let mut qp = paper(PAPER);
+
+        let result = qp.resolve_write_intent(&Write::MarkAs(question(Start(1)), MarkReason::Unsure));
+        assert_eq!(result, WriteResult::Success("Question has been marked as unsure".to_string()));
+
+        qp.resolve_write_intent(&Write::MarkAs(question(Start(2)), MarkReason::Guess));
+        qp.resolve_write_intent(&Write::MarkAs(question(Start(3)), MarkReason::Unsure));
+        qp.resolve_write_intent(&Write::Mark(question(Start(4))));
+
+        assert_eq!(qp.marked[&NodeId(3)].reason, MarkReason::Guess);
+        assert_eq!(
+            meta(&mut qp, MetaIntent::Marked),
+            "You have 4 marked questions: 1 for review, 2 as unsure, 1 as a guess"
+        );
+    }
+
+    #[test]
+    fn unvisited_questions(){
+        let mut qp = paper(PAPER);
+        assert_eq!(qp.unvisited(), vec![2, 3, 4, 6, 7]);
+
+        read(&mut qp, Read::Question(Start(2)));
+        read(&mut qp, Read::Question(Start(1)));
+        qp.resolve_write_intent(&Write::Answer(question(Start(4)), "evaporation".to_string()));
+
+        assert_eq!(qp.unvisited(), vec![4, 7]);
+    }
+
+    #[test]
+    fn marked_meta_carries_payload(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_write_intent(&Write::Mark(question(Start(4))));
+        qp.resolve_write_intent(&Write::MarkAs(question(Start(1)), MarkReason::Guess));
+
+        match qp.resolve_intent(Intent::Meta(MetaIntent::Marked)) {
+            IntentResult::MetaData(payload) => {
+                assert_eq!(payload.message, "You have 2 marked questions: 1 for review, 1 as a guess");
+                assert_eq!(payload.count, 2);
+                assert_eq!(payload.indices, vec![2, 6]);
+            },
+            other => panic!("unexpected result {:?}", other)
+        }
+    }
+
+    #[test]
+    fn navigate_between_marked_questions(){
+        let mut qp = paper(PAPER);
+        assert_eq!(qp.next_marked().unwrap_err(), "You have not marked any questions");
+
+        qp.resolve_write_intent(&Write::Mark(question(Start(5))));
+        qp.resolve_write_intent(&Write::Mark(question(Start(1))));
+        qp.resolve_write_intent(&Write::Mark(question(Start(3))));
+
+        assert_eq!(qp.next_marked().unwrap().data.question_number(), Some(1));
+        assert_eq!(qp.next_marked().unwrap().data.question_number(), Some(3));
+        assert_eq!(qp.next_marked().unwrap().data.question_number(), Some(5));
+        assert_eq!(qp.prev_index(), 7);
+        assert_eq!(qp.next_marked().unwrap_err(), "There are no more marked questions");
+
+        qp.set_wrap(true);
+        assert_eq!(qp.next_marked().unwrap().data.question_number(), Some(1));
+    }
+
+    #[test]
+    fn export_to_markdown(){
+        let qp = paper(PAPER);
+
+        let expected = "# End of Term Exam
+
+## Section A
+
+1. Which gas do plants absorb?
+   - Oxygen
+   - Carbon dioxide
+   - Nitrogen
+
+2. Name the parts of a cell.
+
+3. Define osmosis.
+
+## Section B
+
+4. Explain the water cycle.
+
+5. Describe the structure of an atom.";
+
+        assert_eq!(qp.to_markdown(), expected);
+    }
+
+    #[test]
+    fn review_queue_deduplicates(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_write_intent(&Write::Mark(question(Start(4))));
+        qp.resolve_write_intent(&Write::Skip(question(Start(4))));
+        qp.resolve_write_intent(&Write::Skip(question(Start(2))));
+
+        assert_eq!(qp.review_queue(), vec![3, 6]);
+
+        assert_eq!(qp.next_in_review_queue().unwrap().index, 3);
+        assert_eq!(qp.next_in_review_queue().unwrap().index, 6);
+        assert_eq!(qp.next_in_review_queue().unwrap_err(), "There is nothing more to come back to");
+    }
+
+    #[test]
+    fn ambiguous_number_returns_all_candidates(){
+        let mut qp = sections_paper(&[3, 2]);
+
+        // restart the numbering in section B
+        for (node, number) in qp.nodes[6..].iter_mut().zip(1..) {
+            if let NodeData::Question(ref mut question) = node.data {
+                question.question_number = number;
+            }
+        }
+
+        match qp.resolve_intent(Intent::ReadIntent(Read::NumberedQuestions(2))) {
+            IntentResult::Candidates(Ok(candidates)) => {
+                let found: Vec<(usize, Option<String>)> = candidates.into_iter()
+                    .map(|candidate| (candidate.index, candidate.section))
+                    .collect();
+
+                assert_eq!(found, vec![
+                    (3, Some("Section A".to_string())),
+                    (7, Some("Section B".to_string()))
+                ]);
+            },
+            other => panic!("unexpected result {:?}", other)
+        }
+        assert_eq!(qp.prev_index(), 0);
+
+        match qp.resolve_intent(Intent::ReadIntent(Read::NumberedQuestions(4))) {
+            IntentResult::Candidates(Err(e)) => assert_eq!(e, "There is no question 4"),
+            other => panic!("unexpected result {:?}", other)
+        }
+    }
+
+    #[test]
+    fn find_survives_out_of_range_anchors(){
+        let qp = paper(PAPER);
+
+        assert!(qp.find(predicates::QuestionPredicate, usize::MAX, 1).next().is_none());
+        assert!(qp.find(predicates::QuestionPredicate, 0, usize::MAX).next().is_none());
+        assert_eq!(qp.find(predicates::QuestionPredicate, usize::MAX, 1).rev().count(), 5);
+        assert_eq!(qp.find(predicates::QuestionPredicate, 0, 0).count(), 5);
+    }
+
+    #[test]
+    fn extreme_references_do_not_panic(){
+        let mut qp = paper(PAPER);
+
+        assert_eq!(read(&mut qp, Read::Question(Start(0))).question_number(), Some(1));
+
+        for reference in vec![Start(i32::MAX), Current(i32::MIN), Current(i32::MAX), End(i32::MIN), Absolute(usize::MAX)] {
+            match qp.resolve_intent(Intent::ReadIntent(Read::Question(reference))) {
+                IntentResult::Read(result) => assert!(result.is_err()),
+                other => panic!("unexpected result {:?}", other)
+            }
+        }
+    }
+
+    #[test]
+    fn accessible_text_is_speakable(){
+        let question = |text: &str| NodeData::Question(QuestionData {
+            question: text.to_string(),
+            question_number: 3,
+            ..QuestionData::default()
+        });
+
+        assert_eq!(question("Name **two** _noble_ gases, e.g. neon.").accessible_text(),
+            "Question 3. Name two noble gases, for example neon.");
+        assert_eq!(question("What is 3/4 of 12?").accessible_text(), "Question 3. What is 3 over 4 of 12?");
+        assert_eq!(question("Simplify [math] and / or the    ratio").accessible_text(),
+            "Question 3. Simplify math expression and / or the ratio");
+        assert_eq!(NodeData::Instruction("# Answer `all` questions".to_string()).accessible_text(), "Answer all questions");
+        assert_eq!(NodeData::Document.accessible_text(), "");
+
+        let cases = [
+            ("The answer is No.", "The answer is No."),
+            ("See No. 4 above", "See Number 4 above"),
+            ("What is 6 * 7?", "What is 6 * 7?"),
+            ("Solve for x_1 and x_2", "Solve for x_1 and x_2"),
+            ("Name two gases, e.g., neon", "Name two gases, for example, neon"),
+            ("Use *any* two *methods*", "Use any two methods"),
+            ("What does C# mean?", "What does C# mean?"),
+            ("See (Fig.) 2/3 of it", "See (Figure) 2 over 3 of it"),
+        ];
+
+        for (text, spoken) in cases.iter() {
+            assert_eq!(NodeData::Instruction(text.to_string()).accessible_text(), *spoken);
+        }
+    }
+
+    #[test]
+    fn current_offset_shifts_current_references(){
+        let mut qp = paper(PAPER);
+
+        read(&mut qp, Read::Question(Absolute(3)));
+        assert_eq!(read(&mut qp, Read::Question(Current(0))).question_number(), Some(2));
+        assert_eq!(read(&mut qp, Read::Question(Current(1))).question_number(), Some(3));
+
+        qp.set_current_offset(0);
+        assert_eq!(read(&mut qp, Read::Question(Current(1))).question_number(), Some(3));
+        assert_eq!(read(&mut qp, Read::Question(Current(2))).question_number(), Some(4));
+    }
+
+    #[test]
+    fn notes_serialize_as_json_lines(){
+        let mut qp = paper(PAPER);
+
+        assert_eq!(qp.notes_as_jsonl(), "");
+
+        qp.resolve_intent(Intent::WriteIntent(Write::Note(question(Start(1)), "check units".to_string())));
+        qp.resolve_intent(Intent::WriteIntent(Write::Note(question(Start(4)), "draw a diagram".to_string())));
+
+        let lines: Vec<serde_json::Value> = qp.notes_as_jsonl()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1], serde_json::json!({"index": 6, "question_number": 4, "text": "draw a diagram"}));
+    }
+
+    #[test]
+    fn section_of_finds_the_governing_header(){
+        let qp = paper(PAPER);
+
+        assert_eq!(qp.section_of(3), Some((1, "Section A".to_string())));
+        assert_eq!(qp.section_of(5), Some((5, "Section B".to_string())));
+        assert_eq!(qp.section_of(7), Some((5, "Section B".to_string())));
+        assert_eq!(qp.section_of(0), None);
+    }
+
+    #[test]
+    fn find_can_skip_section_headers(){
+        let qp = paper(PAPER);
+        let any = |_: &NodeIndex| true;
+
+        let found: Vec<usize> = qp.find(any, 1, 1).skip_sections(true).map(|node| node.index()).collect();
+        assert_eq!(found, vec![2, 3, 4, 6, 7]);
+
+        let back: Vec<usize> = qp.find(any, 7, 1).skip_sections(true).rev().map(|node| node.index()).collect();
+        assert_eq!(back, vec![7, 6, 4, 3, 2]);
+
+        assert_eq!(qp.find(any, 1, 1).count(), 7);
+    }
+
+    #[test]
+    fn read_reports_node_status(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_intent(Intent::WriteIntent(Write::Mark(question(Start(2)))));
+        qp.resolve_intent(Intent::WriteIntent(Write::Note(question(Start(2)), "revise".to_string())));
+
+        let outcome = read_outcome(&mut qp, Read::Question(Start(2)));
+        assert_eq!(outcome.data.question_number(), Some(2));
+        assert_eq!(outcome.status, NodeStatus { marked: true, has_note: true, ..NodeStatus::default() });
+
+        assert_eq!(read_outcome(&mut qp, Read::Question(Start(3))).status, NodeStatus::default());
+    }
+
+    #[test]
+    fn numbered_questions_skip_sections(){
+        let qp = sections_paper(&[2, 3]);
+        let questions: Vec<(u32, NodeData)> = qp.numbered_questions().collect();
+
+        assert_eq!(questions.iter().map(|(number, _)| *number).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+
+        for (number, data) in questions {
+            match data {
+                NodeData::Question(question) => assert_eq!(question.question, format!("Question text {}", number)),
+                other => panic!("unexpected node {:?}", other)
+            }
+        }
+    }
+
+    #[test]
+    fn read_from_plays_to_the_end(){
+        let mut qp = paper(PAPER);
+
+        match qp.resolve_intent(Intent::ReadIntent(Read::ReadFrom(Start(3)))) {
+            IntentResult::Playback(Ok(questions)) => {
+                let numbers: Vec<Option<u32>> = questions.iter().map(|data| data.question_number()).collect();
+                assert_eq!(numbers, vec![Some(3), Some(4), Some(5)]);
+            },
+            other => panic!("unexpected result {:?}", other)
+        }
+        assert_eq!(qp.prev_index(), 7);
+
+        match qp.resolve_intent(Intent::ReadIntent(Read::ReadFrom(Start(9)))) {
+            IntentResult::Playback(Err(_)) => (),
+            other => panic!("unexpected result {:?}", other)
+        }
+        assert_eq!(qp.prev_index(), 7);
+    }
+
+    #[test]
+    fn global_notes_are_listed_separately(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_write_intent(&Write::Note(question(Start(2)), "label the nucleus".to_string()));
+
+        match qp.resolve_write_intent(&Write::GlobalNote("check calculator is in radians".to_string())) {
+            WriteResult::Success(message) => assert_eq!(message, "A general note has been taken"),
+            other => panic!("unexpected result {:?}", other)
+        }
+
+        let global: Vec<&str> = qp.global_notes().iter().map(|note| note.note.as_str()).collect();
+        assert_eq!(global, vec!["check calculator is in radians"]);
+        assert!(!qp.status_of(qp.nodes[2].id).has_note);
+
+        assert_eq!(
+            qp.notes_transcript(NoteOrder::default()),
+            "Question 2: label the nucleus\nGeneral\n- check calculator is in radians"
+        );
+    }
+
+    #[test]
+    fn simulate_leaves_the_paper_untouched(){
+        let mut qp = paper(PAPER);
+        read(&mut qp, Read::Question(Start(2)));
+
+        let moved = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let flag = moved.clone();
+        qp.set_on_move(move |_| flag.store(true, std::sync::atomic::Ordering::SeqCst));
+
+        let next = qp.simulate(|copy| {
+            copy.resolve_write_intent(&Write::Mark(question(Current(0))));
+            read(copy, Read::Question(Current(1))).question_number()
+        });
+
+        assert_eq!(next, Some(3));
+        assert_eq!(qp.prev_index(), 3);
+        assert!(qp.marked.is_empty());
+        assert!(!moved.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[test]
+    fn time_remaining_warns_near_the_limit(){
+        let mut qp = sections_paper(&[2]).with_metadata(Metadata {
+            duration_minutes: Some(120),
+            time_limit_minutes: Some(60),
+            ..Metadata::default()
+        });
+
+        assert_eq!(meta(&mut qp, MetaIntent::TimeRemaining(0)), "60 minutes remaining");
+        assert_eq!(meta(&mut qp, MetaIntent::TimeRemaining(55)), "5 minutes remaining");
+        assert_eq!(meta(&mut qp, MetaIntent::TimeRemaining(57)), "Hurry, only 3 minutes remaining");
+        assert_eq!(meta(&mut qp, MetaIntent::TimeRemaining(59)), "Hurry, only 1 minute remaining");
+        assert_eq!(meta(&mut qp, MetaIntent::TimeRemaining(60)), "Time is up");
+        assert_eq!(meta(&mut qp, MetaIntent::TimeRemaining(75)), "Time is up");
+    }
+
+    #[test]
+    fn time_remaining_falls_back_to_duration(){
+        let mut qp = paper(PAPER);
+        assert_eq!(meta(&mut qp, MetaIntent::TimeRemaining(30)), "60 minutes remaining");
+
+        let mut qp = sections_paper(&[2]);
+        assert_eq!(meta(&mut qp, MetaIntent::TimeRemaining(30)), "This paper has no time limit");
+    }
+
+    #[test]
+    fn unanswered_in_current_section(){
+        let mut qp = paper(PAPER);
+        assert!(qp.unanswered_in_section().is_empty());
+
+        qp.resolve_write_intent(&Write::Answer(question(Start(2)), "nucleus".to_string()));
+        read(&mut qp, Read::Question(Start(3)));
+
+        let numbers: Vec<Option<u32>> = qp.unanswered_in_section().iter().map(|data| data.question_number()).collect();
+        assert_eq!(numbers, vec![Some(1), Some(3)]);
+    }
+
+    #[test]
+    fn delete_empty_notes(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_write_intent(&Write::Note(question(Start(1)), "revise gases".to_string()));
+
+        // blank notes are refused when taken, but can still arrive with a restored session
+        qp.notes.push(Note { id: NodeId(3), note: "".to_string() });
+        qp.notes.push(Note { id: NodeId(4), note: "  ".to_string() });
+
+        assert_eq!(qp.delete_notes_where(|note| note.note.trim().is_empty()), 2);
+        assert_eq!(qp.notes_transcript(NoteOrder::default()), "Question 1: revise gases");
+        assert_eq!(qp.delete_notes_where(|note| note.note.trim().is_empty()), 0);
+    }
+
+    #[test]
+    fn marked_index_stays_in_step_with_marks(){
+        let mut qp = sections_paper(&[400, 400]);
+
+        for number in (1..=800).step_by(3) {
+            qp.resolve_write_intent(&Write::MarkNumber(number));
+        }
+
+        let ids: Vec<NodeId> = qp.marked.keys().cloned().collect();
+        for id in ids.iter().step_by(2) {
+            assert!(qp.unmark(*id));
+        }
+        assert!(!qp.unmark(ids[0]));
+
+        let expected = qp.indices_of(qp.marked.keys());
+        assert_eq!(qp.marked_index.iter().cloned().collect::<Vec<_>>(), expected);
+
+        let mut visited = vec![];
+        while let Ok(node) = qp.next_marked() {
+            visited.push(node.index);
+        }
+        assert_eq!(visited, expected);
+
+        qp.clear_marks();
+        assert_eq!(qp.next_marked().unwrap_err(), "You have not marked any questions");
+    }
+
+    #[test]
+    fn sections_can_be_kept_as_nodes(){
+        let qp = paper(PAPER);
+
+        assert_eq!(qp.len(), 8);
+        assert_eq!(qp.last_index(), Some(7));
+        assert_eq!(qp.total_questions(), 5);
+        assert!(qp.nodes[1].data.is_section());
+    }
+
+    #[test]
+    fn sections_can_be_stripped_from_nodes(){
+        let mut builder = QPaperBuilder::new();
+        builder.inline_sections(false);
+
+        let mut qp = build(PAPER, builder);
+
+        assert_eq!(qp.len(), 6);
+        assert_eq!(qp.last_index(), Some(5));
+        assert_eq!(qp.total_questions(), 5);
+        assert!(qp.nodes.iter().all(|node| !node.data.is_section()));
+
+        let sections: Vec<Option<String>> = qp.nodes[1..].iter()
+            .map(|node| match node.data {
+                NodeData::Question(ref question) => question.section.clone(),
+                _ => None
+            })
+            .collect();
+        assert_eq!(sections, vec![
+            Some("Section A".to_string()), Some("Section A".to_string()), Some("Section A".to_string()),
+            Some("Section B".to_string()), Some("Section B".to_string())
+        ]);
+
+        // questions are siblings across the old section boundary
+        assert_eq!(qp.nodes[3].next, Some(4));
+        assert_eq!(qp.nodes[4].parent, Some(0));
+
+        read(&mut qp, Read::Question(Start(3)));
+        assert_eq!(read(&mut qp, Read::Question(Current(1))).question_number(), Some(4));
+    }
+
+    #[test]
+    fn jump_to_the_last_note(){
+        let mut qp = paper(PAPER);
+        assert_eq!(qp.jump_to_last_note().unwrap_err(), "You have not taken any notes");
+
+        qp.resolve_write_intent(&Write::Note(question(Start(4)), "draw the cycle".to_string()));
+        qp.resolve_write_intent(&Write::Note(question(Start(2)), "label the nucleus".to_string()));
+        qp.resolve_write_intent(&Write::GlobalNote("bring a ruler".to_string()));
+        read(&mut qp, Read::Question(Start(5)));
+
+        let node = qp.jump_to_last_note().unwrap();
+        assert_eq!(node.data.question_number(), Some(2));
+        assert_eq!(qp.prev_index(), 3);
+    }
+
+    #[test]
+    fn failed_read_hints_at_the_nearest_match(){
+        let mut qp = paper(PAPER);
+
+        match qp.resolve_intent(Intent::ReadIntent(Read::Question(Start(999)))) {
+            IntentResult::Read(Err(e)) => {
+                assert_eq!(e, "Could not find a next node");
+                assert_eq!(e.hint, Some(7));
+            },
+            other => panic!("unexpected result {:?}", other)
+        }
+
+        read(&mut qp, Read::Question(Start(2)));
+        let error = qp.resolve_read_intent(&Read::Section(Current(-5))).unwrap_err();
+        assert_eq!(error.hint, Some(1));
+
+        let error = qp.resolve_read_intent(&Read::QuestionContaining("gravity".to_string())).unwrap_err();
+        assert_eq!(error.hint, None);
+
+        // reads with their own result shape keep the hint too
+        let hint = |result| match result {
+            IntentResult::FullQuestion(Err(e)) | IntentResult::Playback(Err(e)) | IntentResult::Answered(Err(e)) => e.hint,
+            other => panic!("unexpected result {:?}", other)
+        };
+
+        for read_intent in vec![Read::FullQuestion(Start(999)), Read::ShuffledQuestion(Start(999), 7),
+                                Read::QuestionWithAnswer(Start(999)), Read::ReadFrom(Start(999))] {
+            assert_eq!(hint(qp.resolve_intent(Intent::ReadIntent(read_intent))), Some(7));
+        }
+    }
+
+    #[test]
+    fn question_types_are_inferred(){
+        let qp = paper(PAPER);
+
+        let types: Vec<Option<QuestionType>> = qp.nodes.iter()
+            .filter_map(|node| match node.data {
+                NodeData::Question(ref question) => Some(question.question_type),
+                _ => None
+            })
+            .collect();
+
+        assert_eq!(types, vec![
+            Some(QuestionType::MultipleChoice),
+            Some(QuestionType::ShortAnswer),
+            Some(QuestionType::ShortAnswer),
+            Some(QuestionType::Essay),
+            Some(QuestionType::Essay)
+        ]);
+    }
+
+    #[test]
+    fn question_type_markup_overrides_inference(){
+        let mut qp = tagged_paper("type", &[("Define osmosis.", "essay"), ("Explain the water cycle.", "short answer")]);
+
+        let essay = |reference| Read::QuestionOfType(QuestionType::Essay, reference);
+
+        assert_eq!(read(&mut qp, essay(Start(1))).question_number(), Some(3));
+        assert_eq!(read(&mut qp, essay(Current(1))).question_number(), Some(5));
+        assert_eq!(read(&mut qp, Read::QuestionOfType(QuestionType::MultipleChoice, Start(1))).question_number(), Some(1));
+    }
+
+    #[test]
+    fn nearest_question_in_either_direction(){
+        let qp = paper(PAPER);
+        let nearest = |index| qp.nearest_question(index).map(|node| node.index());
+
+        assert_eq!(nearest(3), Some(3));
+        assert_eq!(nearest(0), Some(2));
+        // the section B header sits between questions 4 and 6, forward wins the tie
+        assert_eq!(nearest(5), Some(6));
+        assert_eq!(nearest(20), Some(7));
+        assert!(paper("<?xml version=\"1.0\"?>\n<root>\n</root>").nearest_question(0).is_none());
+    }
+
+    #[test]
+    fn shuffled_read_maps_letters_back(){
+        let mut qp = paper(PAPER);
+
+        let read_shuffled = |qp: &mut QuestionPaper| match qp.resolve_intent(Intent::ReadIntent(Read::ShuffledQuestion(Start(1), 3))) {
+            IntentResult::FullQuestion(Ok(question)) => question.options.unwrap(),
+            other => panic!("unexpected result {:?}", other)
+        };
+
+        let first = read_shuffled(&mut qp);
+        assert_eq!(first, read_shuffled(&mut qp));
+        assert_eq!(first, vec!["Nitrogen", "Oxygen", "Carbon dioxide"]);
+        assert_eq!(options(&qp, 2), vec!["Oxygen", "Carbon dioxide", "Nitrogen"]);
+
+        // C is read out third, which is carbon dioxide
+        qp.answer_by_letter(&Start(1), 'C');
+        assert_eq!(qp.answer_of(qp.nodes[2].id).map(String::as_str), Some("Carbon dioxide"));
+        assert_eq!(qp.grade(qp.nodes[2].id), Some(true));
+
+        // reading the question as written puts the letters back in their original order
+        let checkpoint = qp.checkpoint();
+        qp.resolve_intent(Intent::ReadIntent(Read::FullQuestion(Start(1))));
+        qp.answer_by_letter(&Start(1), 'A');
+        assert_eq!(qp.answer_of(qp.nodes[2].id).map(String::as_str), Some("Oxygen"));
+
+        // a rollback brings back the shuffled order the checkpoint saw
+        qp.rollback(checkpoint);
+        qp.answer_by_letter(&Start(1), 'A');
+        assert_eq!(qp.answer_of(qp.nodes[2].id).map(String::as_str), Some("Nitrogen"));
+    }
+
+    #[test]
+    fn marked_questions_by_page(){
+        let mut qp = sections_paper(&[10, 13]);
+
+        for number in (1..=23).rev() {
+            qp.resolve_write_intent(&Write::MarkNumber(number));
+        }
+
+        let numbers = |page| -> Vec<u32> {
+            qp.marked_page(page, 10).iter().filter_map(|data| data.question_number()).collect()
+        };
+
+        assert_eq!(numbers(0), (1..=10).collect::<Vec<_>>());
+        assert_eq!(numbers(1), (11..=20).collect::<Vec<_>>());
+        assert_eq!(numbers(2), vec![21, 22, 23]);
+        assert!(numbers(3).is_empty());
+        assert!(qp.marked_page(usize::MAX, usize::MAX).is_empty());
+    }
+
+    #[test]
+    fn note_the_current_question(){
+        let mut qp = paper(PAPER);
+
+        match qp.note_current("too early".to_string()) {
+            WriteResult::Error(e) => assert_eq!(e, "Read a question before taking a note on it"),
+            other => panic!("unexpected result {:?}", other)
+        }
+
+        read(&mut qp, Read::Question(Start(3)));
+
+        match qp.note_current("check the membrane".to_string()) {
+            WriteResult::Success(message) => assert_eq!(message, "A note has been taken"),
+            other => panic!("unexpected result {:?}", other)
+        }
+        assert_eq!(qp.notes_transcript(NoteOrder::default()), "Question 3: check the membrane");
+    }
+
+    #[test]
+    fn marked_and_skipped_as_csv(){
+        let mut qp = paper(PAPER);
+        assert_eq!(qp.marked_numbers_csv(), "");
+
+        qp.resolve_write_intent(&Write::MarkNumber(5));
+        qp.resolve_write_intent(&Write::MarkNumber(1));
+        qp.resolve_write_intent(&Write::MarkNumber(3));
+        qp.resolve_write_intent(&Write::Skip(question(Start(2))));
+
+        assert_eq!(qp.marked_numbers_csv(), "1,3,5");
+        assert_eq!(qp.skipped_numbers_csv(), "2");
+    }
+
+    #[test]
+    fn complete_once_every_question_is_answered(){
+        let mut qp = paper(PAPER);
+        assert!(!qp.is_complete());
+
+        for number in 1..=4 {
+            qp.resolve_write_intent(&Write::Answer(question(Start(number)), "answer".to_string()));
+        }
+        assert!(!qp.is_complete());
+
+        qp.resolve_write_intent(&Write::Answer(question(Start(5)), "answer".to_string()));
+        assert!(qp.is_complete());
+    }
+
+    // the fixture with marks on every question, 2 + 3 + 5 + 10 + 20 = 40 marks
+    fn marks_paper() -> QuestionPaper {
+        tagged_paper("marks", &[("Which gas do plants absorb?", "2"), ("Name the parts of a cell.", "3"), ("Define osmosis.", "5"),
+                                ("Explain the water cycle.", "10"), ("Describe the structure of an atom.", "20")])
+    }
+
+    #[test]
+    fn navigate_by_cumulative_marks(){
+        let mut qp = marks_paper();
+
+        assert_eq!(read(&mut qp, Read::MarksThreshold(50)).question_number(), Some(4));
+        assert_eq!(read(&mut qp, Read::MarksThreshold(51)).question_number(), Some(5));
+        assert_eq!(read(&mut qp, Read::MarksThreshold(25)).question_number(), Some(3));
+        assert_eq!(read(&mut qp, Read::MarksThreshold(0)).question_number(), Some(1));
+        assert_eq!(read(&mut qp, Read::MarksThreshold(100)).question_number(), Some(5));
+        assert_eq!(qp.resolve_read_intent(&Read::MarksThreshold(101)).unwrap_err(), "101 percent is more than the whole paper");
+
+        let mut qp = paper(PAPER);
+        assert_eq!(
+            qp.resolve_read_intent(&Read::MarksThreshold(50)).unwrap_err(),
+            "This paper does not say how many marks each question is worth"
+        );
+    }
+
+    #[test]
+    fn last_index_is_found_from_the_nodes(){
+        let mut qp = QuestionPaper::new(paper(PAPER).nodes, 5);
+
+        assert_eq!(qp.last_index(), Some(7));
+        assert_eq!(read(&mut qp, Read::Question(End(-2))).question_number(), Some(4));
+
+        // it follows edits to the nodes
+        qp.edit_nodes(|nodes| nodes.truncate(5));
+        assert_eq!(qp.last_index(), Some(4));
+        assert_eq!(read(&mut qp, Read::Question(End(-1))).question_number(), Some(3));
+
+        // a paper with no questions has no last question to anchor on
+        let mut empty = paper("<?xml version=\"1.0\"?>\n<root>\n</root>");
+        assert_eq!(empty.last_index(), None);
+        assert!(empty.resolve_read_intent(&Read::Question(End(-1))).is_err());
+    }
+
+    #[test]
+    fn previous_section_crosses_boundaries(){
+        let mut qp = sections_paper(&[2, 2, 2]);
+
+        // C header is 7, its first question 8
+        read(&mut qp, Read::Question(Start(5)));
+        assert_eq!(qp.previous_section().unwrap().data.section_name(), Some("Section B"));
+        assert_eq!(qp.prev_index(), 4);
+
+        assert_eq!(qp.previous_section().unwrap().data.section_name(), Some("Section A"));
+        assert_eq!(qp.prev_index(), 1);
+
+        assert_eq!(qp.previous_section().unwrap_err(), "You are already in the first section");
+        assert_eq!(qp.prev_index(), 1);
+
+        read(&mut qp, Read::Question(Start(2)));
+        assert_eq!(qp.previous_section().unwrap_err(), "You are already in the first section");
+    }
+
+    #[test]
+    fn free_text_answers_are_graded_against_the_key(){
+        let mut qp = tagged_paper("answer", &[("Define osmosis.", "Diffusion of water")]);
+
+        match qp.resolve_write_intent(&Write::AnswerText(question(Start(3)), "  diffusion of   WATER. ".to_string())) {
+            WriteResult::Success(message) => assert_eq!(message, "Your answer has been recorded"),
+            other => panic!("unexpected result {:?}", other)
+        }
+        assert_eq!(qp.grade(qp.nodes[4].id), Some(true));
+
+        match qp.resolve_write_intent(&Write::AnswerText(question(Start(2)), "nucleus".to_string())) {
+            WriteResult::Success(message) => assert_eq!(message, "Your answer has been recorded and requires manual grading"),
+            other => panic!("unexpected result {:?}", other)
+        }
+        assert_eq!(qp.grade(qp.nodes[3].id), None);
+
+        match qp.resolve_write_intent(&Write::AnswerText(question(Start(1)), "carbon dioxide".to_string())) {
+            WriteResult::Error(e) => assert_eq!(e, "This question has options, answer it with a letter"),
+            other => panic!("unexpected result {:?}", other)
+        }
+    }
+
+    #[test]
+    fn read_a_question_with_its_answer(){
+        let mut qp = paper(PAPER);
+        qp.resolve_write_intent(&Write::Answer(question(Start(5)), "protons and neutrons".to_string()));
+
+        let with_answer = |qp: &mut QuestionPaper, number| match qp.resolve_intent(Intent::ReadIntent(Read::QuestionWithAnswer(Start(number)))) {
+            IntentResult::Answered(Ok(answered)) => answered,
+            other => panic!("unexpected result {:?}", other)
+        };
+
+        let answered = with_answer(&mut qp, 5);
+        assert_eq!(answered.data.question_number(), Some(5));
+        assert!(answered.answered);
+        assert_eq!(answered.answer, Some("protons and neutrons".to_string()));
+        assert_eq!(qp.prev_index(), 7);
+
+        let unanswered = with_answer(&mut qp, 2);
+        assert_eq!(unanswered.data.question_number(), Some(2));
+        assert!(!unanswered.answered);
+        assert_eq!(unanswered.answer, None);
+    }
+
+    #[test]
+    fn repeat_the_last_read_and_mark(){
+        let mut qp = paper(PAPER);
+
+        match qp.repeat_last() {
+            IntentResult::Meta(message) => assert_eq!(message, "There is nothing to repeat"),
+            other => panic!("unexpected result {:?}", other)
+        }
+
+        read(&mut qp, Read::Question(Start(2)));
+        assert_eq!(read(&mut qp, Read::Question(Current(1))).question_number(), Some(3));
+
+        // repeating a relative read re-reads the same question instead of moving on
+        match qp.repeat_last() {
+            IntentResult::Read(Ok(ReadOutcome { data, .. })) => assert_eq!(data.question_number(), Some(3)),
+            other => panic!("unexpected result {:?}", other)
+        }
+        assert_eq!(qp.prev_index(), 4);
+
+        qp.resolve_intent(Intent::WriteIntent(Write::Mark(question(Current(0)))));
+        qp.unmark(qp.nodes[4].id);
+
+        match qp.repeat_last() {
+            IntentResult::Write(WriteResult::Success(message)) => assert_eq!(message, "Question has been marked for review"),
+            other => panic!("unexpected result {:?}", other)
+        }
+        assert_eq!(qp.marked_numbers_csv(), "3");
+    }
+
+    #[test]
+    fn time_is_charged_to_the_node_left(){
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let mut qp = paper(PAPER);
+        assert_eq!(meta(&mut qp, MetaIntent::SlowestQuestions), "No time has been recorded yet");
+
+        let now = Arc::new(AtomicU64::new(0));
+        let clock = now.clone();
+        qp.set_clock(move || clock.load(Ordering::SeqCst));
+
+        let mut at = |qp: &mut QuestionPaper, seconds, number| {
+            now.store(seconds, Ordering::SeqCst);
+            read(qp, Read::Question(Start(number)));
+        };
+
+        at(&mut qp, 0, 1);
+        at(&mut qp, 30, 2);
+        at(&mut qp, 150, 1);
+        at(&mut qp, 160, 3);
+        at(&mut qp, 220, 4);
+
+        assert_eq!(qp.time_on(2), 40);
+        assert_eq!(qp.time_on(3), 120);
+        assert_eq!(qp.time_on(4), 60);
+        assert_eq!(qp.time_on(6), 0);
+        assert_eq!(
+            meta(&mut qp, MetaIntent::SlowestQuestions),
+            "Most time went on question 2 (120 seconds), question 3 (60 seconds), question 1 (40 seconds)"
+        );
+    }
+
+    #[test]
+    fn prune_stale_entries(){
+        let mut qp = paper(PAPER);
+        let stale = NodeId(99);
+
+        qp.resolve_write_intent(&Write::MarkNumber(2));
+        qp.resolve_write_intent(&Write::GlobalNote("bring a ruler".to_string()));
+        qp.marked.insert(stale, Mark { data: NodeData::Document, reason: MarkReason::default() });
+        qp.skipped.insert(stale, NodeData::Document);
+        qp.record_answer(stale, "B".to_string());
+        qp.notes.push(Note { id: stale, note: "gone".to_string() });
+
+        assert_eq!(qp.prune_invalid(), 4);
+        assert_eq!(qp.marked_numbers_csv(), "2");
+        assert_eq!(qp.num_skipped(), 0);
+        assert_eq!(qp.global_notes().len(), 1);
+        assert_eq!(qp.prune_invalid(), 0);
+    }
+
+    #[test]
+    fn group_questions_by_difficulty(){
+        let groups = tagged_paper("difficulty", &[("Which gas do plants absorb?", "easy"), ("Define osmosis.", "Hard"),
+                                                  ("Explain the water cycle.", "easy"), ("Describe the structure of an atom.", "hard")])
+            .by_difficulty();
+        let numbers = |difficulty| -> Vec<Option<u32>> {
+            groups.get(&difficulty).map(|group| group.iter().map(|data| data.question_number()).collect()).unwrap_or_default()
+        };
+
+        assert_eq!(numbers(Difficulty::Easy), vec![Some(1), Some(4)]);
+        assert_eq!(numbers(Difficulty::Hard), vec![Some(3), Some(5)]);
+        assert!(numbers(Difficulty::Medium).is_empty());
+    }
+
+    #[test]
+    fn reads_report_the_distance_moved(){
+        let mut qp = paper(PAPER);
+
+        let distance = |qp: &mut QuestionPaper, reference| read_outcome(qp, Read::Question(reference)).distance;
+
+        assert_eq!(distance(&mut qp, Start(1)), 1);
+        // the section B header between questions 3 and 4 is not counted
+        assert_eq!(distance(&mut qp, Start(5)), 4);
+        assert_eq!(distance(&mut qp, Current(-3)), -3);
+        assert_eq!(distance(&mut qp, Current(0)), 0);
+    }
+
+    #[test]
+    fn skip_with_note_records_both(){
+        let mut qp = paper(PAPER);
+
+        let result = qp.resolve_write_intent(&Write::SkipWithNote(question(Start(2)), "too long".to_string()));
+        assert_eq!(result, WriteResult::Success("Question has been skipped with a note".to_string()));
+
+        assert_eq!(qp.skipped.len(), 1);
+        assert_eq!(qp.notes.len(), 1);
+        assert_eq!(qp.notes[0].note, "too long");
+        assert!(qp.skipped.contains_key(&qp.notes[0].id));
+    }
+
+    #[test]
+    fn skip_with_note_rolls_back_when_the_note_is_refused(){
+        let mut qp = paper(PAPER);
+
+        let result = qp.resolve_write_intent(&Write::SkipWithNote(question(Start(2)), "  ".to_string()));
+        assert_eq!(result, WriteResult::Error(Borrowed("A note cannot be empty")));
+
+        assert!(qp.skipped.is_empty());
+        assert!(qp.notes.is_empty());
+    }
+
+    #[test]
+    fn annotated_questions_pair_questions_with_their_notes(){
+        let mut qp = paper(PAPER);
+        qp.resolve_write_intent(&Write::Note(question(Start(3)), "draw a diagram".to_string()));
+        qp.resolve_write_intent(&Write::Note(question(Start(1)), "revise gases".to_string()));
+        qp.resolve_write_intent(&Write::Note(question(Start(3)), "label the membrane".to_string()));
+        qp.resolve_write_intent(&Write::GlobalNote("check the time".to_string()));
+
+        let annotated = qp.annotated_questions();
+        assert_eq!(annotated.len(), 2);
+
+        let (first, notes) = &annotated[0];
+        assert_eq!(first, &qp.nodes[2].data);
+        assert_eq!(notes.iter().map(|note| note.note.as_str()).collect::<Vec<_>>(), vec!["revise gases"]);
+
+        let (second, notes) = &annotated[1];
+        assert_eq!(second, &qp.nodes[4].data);
+        assert_eq!(
+            notes.iter().map(|note| note.note.as_str()).collect::<Vec<_>>(),
+            vec!["draw a diagram", "label the membrane"]
+        );
+    }
+
+    #[test]
+    fn nth_question_from_end_agrees_with_a_backward_walk(){
+        let mut qp = sections_paper(&[3, 0, 4, 2]);
+
+        let walk = |qp: &QuestionPaper, n: usize| {
+            qp.find(predicates::QuestionPredicate, qp.last_index().unwrap(), n).rev().next().map(|node| node.index())
+        };
+
+        for n in 1..=qp.total_questions() as usize + 1 {
+            let expected = walk(&qp, n);
+            assert_eq!(qp.nth_question_from_end(n).map(|node| node.index()), expected, "n = {}", n);
+        }
+
+        // the cached positions follow edits to the nodes
+        let mut inserted = qp.nodes[2].clone();
+        inserted.id = NodeId(qp.len());
+        qp.edit_nodes(|nodes| nodes.push(inserted));
+
+        let last = qp.len() - 1;
+        assert_eq!(qp.nth_question_from_end(1).map(|node| node.index()), Some(last));
+
+        // a question inserted earlier shifts the one cached as last
+        let mut early = paper(PAPER);
+        assert_eq!(early.nth_question_from_end(1).map(|node| node.index()), Some(7));
+
+        let mut inserted = early.nodes[2].clone();
+        inserted.id = NodeId(early.len());
+        early.resolve_write_intent(&Write::Mark(question(End(-1))));
+        early.edit_nodes(|nodes| nodes.insert(3, inserted));
+        assert_eq!(read(&mut early, Read::Question(End(-1))), early.nodes[8].data);
+
+        // the marked index follows the edit too
+        assert_eq!(early.marked_index.iter().cloned().collect::<Vec<_>>(), vec![8]);
+        assert_eq!(read(&mut qp, Read::Question(End(-1))), qp.nodes[last].data);
+        assert_eq!(qp.nth_question_from_end(1000).map(|node| node.index()), None);
+    }
+
+    #[test]
+    fn announce_the_section_once_when_entering_it(){
+        let mut qp = paper(PAPER);
+        qp.set_announce_sections(true);
+
+        let announced = |qp: &mut QuestionPaper, reference| read_outcome(qp, Read::Question(reference)).section;
+
+        assert_eq!(announced(&mut qp, Start(1)), Some("Section A".to_string()));
+        assert_eq!(announced(&mut qp, Current(1)), None);
+        assert_eq!(announced(&mut qp, Current(1)), None);
+
+        // crossing into section B
+        assert_eq!(announced(&mut qp, Current(1)), Some("Section B".to_string()));
+        assert_eq!(announced(&mut qp, Current(1)), None);
+
+        // going back announces section A again
+        assert_eq!(announced(&mut qp, Start(2)), Some("Section A".to_string()));
+
+        qp.set_announce_sections(false);
+        assert_eq!(announced(&mut qp, Start(4)), None);
+    }
+
+    #[test]
+    fn read_only_paper_rejects_writes(){
+        let mut qp = paper(PAPER);
+        qp.resolve_write_intent(&Write::Mark(question(Start(1))));
+        qp.set_read_only(true);
+
+        let writes = vec![
+            Write::Mark(question(Start(2))),
+            Write::Skip(question(Start(2))),
+            Write::Note(question(Start(2)), "revise".to_string()),
+            Write::Answer(question(Start(1)), "B".to_string()),
+            Write::GlobalNote("check the time".to_string()),
+        ];
+
+        for write in writes {
+            match qp.resolve_intent(Intent::WriteIntent(write)) {
+                IntentResult::Write(result) => assert_eq!(result, WriteResult::Error(Borrowed("The paper is read-only"))),
+                other => panic!("unexpected result {:?}", other)
+            }
+        }
+
+        // the direct writers refuse too
+        read(&mut qp, Read::Question(Start(3)));
+        let refused = WriteResult::Error(Borrowed("The paper is read-only"));
+        assert_eq!(qp.mark_current(), refused);
+        assert_eq!(qp.note_current("revise".to_string()), refused);
+        assert_eq!(qp.answer_by_letter(&Start(1), 'B'), refused);
+        assert!(!qp.unmark(qp.nodes[2].id));
+        qp.clear_marks();
+
+        assert_eq!(qp.num_marked(), 1);
+        assert!(qp.skipped.is_empty());
+        assert!(qp.notes.is_empty());
+        assert!(qp.answered.is_empty());
+
+        // reads and meta queries are unaffected
+        assert_eq!(read(&mut qp, Read::Question(Start(2))).question_number(), Some(2));
+        assert!(matches!(qp.resolve_intent(Intent::Meta(MetaIntent::Marked)), IntentResult::MetaData(ref payload) if payload.count == 1));
+
+        qp.set_read_only(false);
+        qp.resolve_write_intent(&Write::Skip(question(Start(2))));
+        assert_eq!(qp.skipped.len(), 1);
+    }
+
+    #[test]
+    fn resume_after_the_last_marked_question(){
+        let mut qp = paper(PAPER);
+        qp.resolve_write_intent(&Write::Mark(question(Start(4))));
+        qp.resolve_write_intent(&Write::Mark(question(Start(2))));
+        qp.resolve_intent(Intent::ReadIntent(Read::Question(Start(1))));
+
+        // the question after q4 is q5, past the cursor and the earlier mark
+        assert_eq!(qp.resume_after_marked().unwrap().data.question_number(), Some(5));
+        assert_eq!(qp.prev_index(), 7);
+
+        qp.resolve_write_intent(&Write::Mark(question(Start(5))));
+        assert_eq!(qp.resume_after_marked().unwrap_err(), "There are no questions after the last marked one");
+    }
+
+    #[test]
+    fn resume_without_marks_starts_at_the_first_question(){
+        let mut qp = paper(PAPER);
+        qp.resolve_intent(Intent::ReadIntent(Read::Question(Start(3))));
+
+        assert_eq!(qp.resume_after_marked().unwrap().data.question_number(), Some(1));
+        assert_eq!(qp.prev_index(), 2);
+    }
+
+    const PARTS_PAPER: &str = r#"<?xml version="1.0"?>
+<root>
+<SECTION_A>
+<section_number>Section A</section_number>
+<item><question>Answer about forces.</question><part>Define a force.</part><part>State its SI unit.</part><part>Give an example.</part></item>
+<item><question>Define momentum.</question></item>
+<item><question>Answer about energy.</question><part>Define kinetic energy.</part></item>
+</SECTION_A>
+</root>
+"#;
+
+    #[test]
+    fn sub_parts_are_not_counted_as_questions(){
+        let qp = paper(PARTS_PAPER);
+
+        assert_eq!(qp.total_questions(), 3);
+        assert_eq!(qp.find_all(predicates::QuestionPredicate).len(), 3);
+
+        let parts: Vec<(String, u32)> = qp.find_all(predicates::SubPartPredicate)
+            .iter()
+            .map(|node| match node.data() {
+                NodeData::SubPart(ref part) => (part.label.clone(), part.question_number),
+                other => panic!("unexpected node {:?}", other)
+            })
+            .collect();
+
+        assert_eq!(parts, vec![
+            ("a".to_string(), 1), ("b".to_string(), 1), ("c".to_string(), 1), ("a".to_string(), 3)
+        ]);
+        assert_eq!(qp.nodes[3].parent, Some(2));
+        assert_eq!(qp.nodes[3].data.accessible_text(), "Part a. Define a force.");
+    }
+
+    #[test]
+    fn navigate_between_sub_parts(){
+        let mut qp = paper(PARTS_PAPER);
+        assert_eq!(qp.next_part().unwrap_err(), "Read a question before moving between its parts");
+
+        read(&mut qp, Read::Question(Start(1)));
+        assert_eq!(qp.next_part().unwrap().index, 3);
+        assert_eq!(qp.next_part().unwrap().index, 4);
+        assert_eq!(qp.next_part().unwrap().index, 5);
+        assert_eq!(qp.next_part().unwrap_err(), "There are no more parts to this question");
+
+        assert_eq!(qp.previous_part().unwrap().index, 4);
+        assert_eq!(qp.previous_part().unwrap().index, 3);
+        assert_eq!(qp.previous_part().unwrap_err(), "This is the first part of the question");
+
+        // question reads step over the parts
+        assert_eq!(read(&mut qp, Read::Question(Current(1))).question_number(), Some(2));
+        assert_eq!(qp.next_part().unwrap_err(), "There are no more parts to this question");
+
+        assert_eq!(read(&mut qp, Read::Question(Current(1))).question_number(), Some(3));
+        assert_eq!(qp.next_part().unwrap().index, 8);
+    }
+
+    #[test]
+    fn search_ranks_questions_by_relevance(){
+        let mut qp = paper(PAPER);
+        let texts = [
+            "Explain how plant cells store energy.",
+            "Describe photosynthesis in green plants.",
+            "State the products of photosynthesis.",
+            "Name the parts of a flower.",
+            "Define osmosis.",
+        ];
+
+        for (node, text) in qp.nodes.iter_mut().filter(|node| node.data.is_question()).zip(texts.iter()) {
+            if let NodeData::Question(ref mut question) = node.data {
+                question.question = text.to_string();
+            }
+        }
+
+        let ranked: Vec<Option<u32>> = qp.search("Photosynthesis plants")
+            .iter()
+            .map(|(_, data)| data.question_number())
+            .collect();
+
+        // both words, then one exact word, then a near miss on "plant"
+        assert_eq!(ranked, vec![Some(2), Some(3), Some(1)]);
+
+        assert_eq!(qp.search("photosynthsis").len(), 2);
+        assert!(qp.search("gravity").is_empty());
+        assert!(qp.search("  ").is_empty());
+    }
+
+    #[test]
+    fn borrowed_reads_point_into_the_paper(){
+        let mut qp = paper(PAPER);
+        let mut owned = paper(PAPER);
+
+        for read in vec![Read::Question(Start(2)), Read::Question(Current(1)), Read::Section(Start(2)), Read::Question(End(-1))] {
+            let expected = owned.resolve_intent(Intent::ReadIntent(read.clone()));
+
+            let (pointer, result) = match qp.resolve_intent_ref(&Intent::ReadIntent(read)) {
+                IntentResultRef::Read(Ok(outcome)) => {
+                    let ReadOutcome { data, status, distance, section } = outcome;
+
+                    (data as *const NodeData, ReadOutcome { data: data.clone(), status, distance, section })
+                },
+                other => panic!("unexpected result {:?}", other)
+            };
+
+            // no copy was made, the data is the node's own
+            assert!(std::ptr::eq(pointer, &qp.nodes[qp.prev_index()].data));
+
+            match expected {
+                IntentResult::Read(Ok(expected)) => assert_eq!(result, expected),
+                other => panic!("unexpected result {:?}", other)
+            }
+        }
+
+        assert_eq!(qp.prev_index(), owned.prev_index());
+        assert!(matches!(qp.resolve_intent_ref(&Intent::ReadIntent(Read::Question(Start(9)))), IntentResultRef::Read(Err(_))));
+
+        // everything else is resolved as usual
+        assert!(matches!(qp.resolve_intent_ref(&Intent::Meta(MetaIntent::Marked)), IntentResultRef::Owned(IntentResult::MetaData(_))));
+    }
+
+    #[test]
+    fn mark_current_marks_the_node_just_read(){
+        let mut qp = paper(PAPER);
+        assert_eq!(qp.mark_current(), WriteResult::Error(Borrowed("Read a question before marking it")));
+        assert_eq!(qp.num_marked(), 0);
+
+        read(&mut qp, Read::Question(Start(3)));
+        assert_eq!(qp.mark_current(), WriteResult::Success("Question has been marked for review".to_string()));
+
+        assert_eq!(qp.num_marked(), 1);
+        assert!(qp.status_of(qp.nodes[4].id).marked);
+        assert!(qp.marked_index.contains(&4));
+    }
+
+    #[test]
+    fn count_questions_in_each_section(){
+        let qp = sections_paper(&[3, 1, 4]);
+
+        assert_eq!(qp.section_question_counts(), vec![
+            ("Section A".to_string(), 3),
+            ("Section B".to_string(), 1),
+            ("Section C".to_string(), 4),
+        ]);
+    }
+
+    #[test]
+    fn jump_to_a_percentage_of_the_questions(){
+        let mut qp = paper(PAPER);
+
+        assert_eq!(qp.jump_to_percent(0).unwrap().data.question_number(), Some(1));
+        assert_eq!(qp.jump_to_percent(50).unwrap().data.question_number(), Some(3));
+        assert_eq!(qp.prev_index(), 4);
+        assert_eq!(qp.jump_to_percent(100).unwrap().data.question_number(), Some(5));
+        assert_eq!(qp.jump_to_percent(250).unwrap().data.question_number(), Some(5));
+
+        // positions follow the questions, not the section headers between them
+        let mut qp = sections_paper(&[1, 1, 8]);
+        assert_eq!(qp.jump_to_percent(50).unwrap().data.question_number(), Some(6));
+    }
+
+    #[test]
+    fn refuse_to_skip_an_answered_question(){
+        let mut qp = paper(PAPER);
+        qp.resolve_write_intent(&Write::Answer(question(Start(2)), "nucleus".to_string()));
+
+        let refused = WriteResult::Error(Borrowed("You've already answered this question"));
+        assert_eq!(qp.resolve_write_intent(&Write::Skip(question(Start(2)))), refused);
+        assert_eq!(qp.resolve_write_intent(&Write::SkipWithNote(question(Start(2)), "later".to_string())), refused);
+        assert!(qp.skipped.is_empty());
+        assert!(qp.notes.is_empty());
+
+        // unanswered questions skip as before
+        assert_eq!(qp.resolve_write_intent(&Write::Skip(question(Start(3)))), WriteResult::Success("Question has been skipped".to_string()));
+
+        qp.set_allow_skipping_answered(true);
+        assert_eq!(qp.resolve_write_intent(&Write::Skip(question(Start(2)))), WriteResult::Success("Question has been skipped".to_string()));
+        assert_eq!(qp.skipped.len(), 2);
+    }
+
+    #[test]
+    fn build_from_a_schema(){
+        let schema = PaperSchema {
+            title: Some("End of Term Exam".to_string()),
+            duration_minutes: Some(90),
+            sections: vec![
+                SectionSchema {
+                    name: "Section A".to_string(),
+                    questions: vec![
+                        QuestionSchema {
+                            question: "Which gas do plants absorb?".to_string(),
+                            options: vec!["Oxygen".to_string(), "Carbon dioxide".to_string()],
+                            answer: Some("B".to_string()),
+                            ..QuestionSchema::default()
+                        },
+                        QuestionSchema {
+                            question: "Answer about forces.".to_string(),
+                            parts: vec!["Define a force.".to_string(), "State its SI unit.".to_string()],
+                            marks: Some(4),
+                            ..QuestionSchema::default()
+                        },
+                    ]
+                },
+                SectionSchema {
+                    name: "Section B".to_string(),
+                    questions: vec![QuestionSchema {
+                        question: "Explain the water cycle.".to_string(),
+                        question_type: Some("essay".to_string()),
+                        difficulty: Some("hard".to_string()),
+                        ..QuestionSchema::default()
+                    }]
+                },
+            ],
+            ..PaperSchema::default()
+        };
+
+        let mut qp = QPaperBuilder::new().build_schema(schema);
+
+        assert_eq!(qp.total_questions(), 3);
+        assert_eq!(qp.metadata().title.as_deref(), Some("End of Term Exam"));
+        assert_eq!(qp.section_question_counts(), vec![("Section A".to_string(), 2), ("Section B".to_string(), 1)]);
+        assert_eq!(qp.find_all(predicates::SubPartPredicate).len(), 2);
+
+        match read(&mut qp, Read::Question(Start(1))) {
+            NodeData::Question(question) => {
+                assert_eq!(question.question_type, Some(QuestionType::MultipleChoice));
+                assert_eq!(question.grade("Carbon dioxide"), Some(true));
+            },
+            other => panic!("unexpected node {:?}", other)
+        }
+
+        match read(&mut qp, Read::Question(End(-1))) {
+            NodeData::Question(question) => {
+                assert_eq!(question.question_number, 3);
+                assert_eq!(question.difficulty, Some(Difficulty::Hard));
+            },
+            other => panic!("unexpected node {:?}", other)
+        }
+
+        // the same paper from json
+        let schema: PaperSchema = serde_json::from_str(r#"{
+            "sections": [{ "name": "Section A", "questions": [{ "question": "Define osmosis.", "type": "short answer" }] }]
+        }"#).unwrap();
+
+        let qp = QPaperBuilder::new().build_schema(schema.clone());
+        assert_eq!(qp.total_questions(), 1);
+        assert!(matches!(qp.nodes[2].data, NodeData::Question(ref question) if question.question_type == Some(QuestionType::ShortAnswer)));
+
+        // the builder's settings apply to a schema as they do to markup
+        let mut builder = QPaperBuilder::new();
+        builder.inline_sections(false);
+
+        let qp = builder.build_schema(schema);
+        assert!(qp.nodes.iter().all(|node| !node.data.is_section()));
+        assert!(matches!(qp.nodes[1].data, NodeData::Question(ref question) if question.section.as_deref() == Some("Section A")));
+
+        let options = |builder: &mut QPaperBuilder| {
+            let qp = builder.build_schema(PaperSchema {
+                sections: vec![SectionSchema {
+                    name: "Section A".to_string(),
+                    questions: vec![QuestionSchema {
+                        question: "Which gas do plants absorb?".to_string(),
+                        options: vec!["Oxygen".to_string(), "Carbon dioxide".to_string(), "Nitrogen".to_string()],
+                        answer: Some("B".to_string()),
+                        ..QuestionSchema::default()
+                    }]
+                }],
+                ..PaperSchema::default()
+            });
+
+            match qp.nodes[2].data {
+                NodeData::Question(ref question) => (question.options.clone(), question.original_order.clone()),
+                ref other => panic!("unexpected node {:?}", other)
+            }
+        };
+
+        let mut shuffled = QPaperBuilder::new();
+        shuffled.shuffle_options(7);
+
+        let (plain, order) = options(&mut QPaperBuilder::new());
+        assert!(order.is_empty());
+
+        let (mixed, order) = options(&mut shuffled);
+        assert_eq!(order, builder::shuffled_order(3, 1, 7));
+        assert_eq!(mixed, order.iter().map(|position| plain[*position].clone()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn report_the_marks_answered_so_far(){
+        let mut qp = marks_paper();
+        assert_eq!(qp.total_marks(), Some(40));
+        assert_eq!(meta(&mut qp, MetaIntent::MarkBudget), "You've secured answers worth 0 of 40 marks");
+
+        // attempts count whether or not they are right
+        qp.resolve_write_intent(&Write::Answer(question(Start(1)), "Oxygen".to_string()));
+        qp.resolve_write_intent(&Write::Answer(question(Start(4)), "evaporation".to_string()));
+        assert_eq!(meta(&mut qp, MetaIntent::MarkBudget), "You've secured answers worth 12 of 40 marks");
+
+        let mut qp = paper(PAPER);
+        assert_eq!(qp.total_marks(), None);
+        assert_eq!(meta(&mut qp, MetaIntent::MarkBudget), "Marks are not available for this paper");
+    }
+
+    #[test]
+    fn step_through_every_node(){
+        let mut qp = paper(PARTS_PAPER);
+
+        let mut visited = vec![read(&mut qp, Read::Any(Start(1)))];
+        while let Ok(node) = qp.resolve_read_intent(&Read::Any(Current(1))) {
+            qp.visit(&node);
+            visited.push(node.data);
+        }
+
+        let kinds: Vec<&str> = visited.iter()
+            .map(|data| match data {
+                NodeData::Section(_) => "section",
+                NodeData::Question(_) => "question",
+                NodeData::SubPart(_) => "part",
+                _ => "other"
+            })
+            .collect();
+
+        assert_eq!(kinds, vec!["section", "question", "part", "part", "part", "question", "question", "part"]);
+        assert_eq!(qp.prev_index(), qp.len() - 1);
+
+        // and back again, stopping before the document root
+        assert!(read(&mut qp, Read::Any(Current(-1))).is_question());
+        assert!(read(&mut qp, Read::Any(Current(-1))).is_question());
+        assert!(matches!(read(&mut qp, Read::Any(Start(1))), NodeData::Section(_)));
+        assert!(qp.resolve_read_intent(&Read::Any(Current(-1))).is_err());
+    }
+
+    #[test]
+    fn keep_every_answer_given(){
+        let mut qp = paper(PAPER);
+        assert!(qp.answer_history(2).is_empty());
+
+        qp.resolve_write_intent(&Write::Answer(question(Start(1)), "Oxygen".to_string()));
+        qp.resolve_write_intent(&Write::Answer(question(Start(1)), "Carbon dioxide".to_string()));
+
+        assert_eq!(qp.answer_history(2), &["Oxygen".to_string(), "Carbon dioxide".to_string()][..]);
+
+        // the latest attempt is the answer that counts
+        let id = qp.nodes[2].id;
+        assert_eq!(qp.answer_of(id), Some(&"Carbon dioxide".to_string()));
+        assert_eq!(qp.grade(id), Some(true));
+        assert!(qp.answer_history(99).is_empty());
+    }
+
+    #[test]
+    fn searches_stop_at_the_node_limit(){
+        let mut qp = sections_paper(&[20, 20]);
+        qp.set_max_search_nodes(Some(10));
+
+        let error = qp.resolve_read_intent(&Read::Question(Start(1_000_000))).unwrap_err();
+        assert_eq!(error, "Search limit reached");
+        assert_eq!(error.hint, None);
+
+        // matches within the limit are still found
+        assert_eq!(read(&mut qp, Read::Question(Start(5))).question_number(), Some(5));
+
+        qp.set_max_search_nodes(None);
+        assert_eq!(qp.resolve_read_intent(&Read::Question(Start(1_000_000))).unwrap_err(), "Could not find a next node");
+        assert_eq!(read(&mut qp, Read::Question(Start(30))).question_number(), Some(30));
+    }
+
+    #[test]
+    fn count_the_words_in_the_questions(){
+        // 5 + 4 option words, then 6, 2, 4 and 6, the section names are not counted
+        let mut qp = paper(PAPER);
+        assert_eq!(qp.total_word_count(), 27);
+        assert_eq!(meta(&mut qp, MetaIntent::Length), "This paper is about 27 words");
+
+        // three words a question, the parts count too
+        let qp = paper(PARTS_PAPER);
+        assert_eq!(qp.total_word_count(), 3 + 3 + 4 + 3 + 2 + 3 + 3);
+
+        let mut qp = sections_paper(&[300, 150]);
+        assert_eq!(qp.total_word_count(), 1350);
+        assert_eq!(meta(&mut qp, MetaIntent::Length), "This paper is about 1,400 words");
+        assert_eq!(thousands(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn restore_only_the_cursor(){
+        let mut qp = paper(PAPER);
+        qp.resolve_write_intent(&Write::Mark(question(Start(1))));
+        qp.resolve_write_intent(&Write::Note(question(Start(2)), "revise".to_string()));
+
+        qp.restore_position(4);
+        assert_eq!(qp.prev_index(), 4);
+        assert_eq!(qp.max_reached, 4);
+        assert_eq!(read(&mut qp, Read::Question(Current(1))).question_number(), Some(4));
+
+        // past the end lands on the last node
+        qp.restore_position(500);
+        assert_eq!(qp.prev_index(), 7);
+        assert_eq!(qp.max_reached, 7);
+
+        assert_eq!(qp.num_marked(), 1);
+        assert_eq!(qp.notes.len(), 1);
+    }
+
+    #[test]
+    fn restoring_the_cursor_is_a_move(){
+        use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+        let mut qp = paper(PAPER);
+        qp.set_announce_sections(true);
+
+        let moves = Arc::new(AtomicUsize::new(0));
+        let counter = moves.clone();
+        qp.set_on_move(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let now = Arc::new(AtomicU64::new(0));
+        let clock = now.clone();
+        qp.set_clock(move || clock.load(Ordering::SeqCst));
+
+        read(&mut qp, Read::Question(Start(1)));
+        now.store(20, Ordering::SeqCst);
+        qp.restore_position(6);
+        now.store(50, Ordering::SeqCst);
+        read(&mut qp, Read::Question(Start(1)));
+
+        assert_eq!(moves.load(Ordering::SeqCst), 3);
+        assert_eq!(qp.time_on(2), 20);
+        assert_eq!(qp.time_on(6), 30);
+
+        // the restored node has not been read, and the section is announced afresh
+        qp.restore_position(3);
+        assert!(qp.last_read().is_none());
+        assert_eq!(qp.mark_current(), WriteResult::Error(Borrowed("Read a question before marking it")));
+
+        assert_eq!(read_outcome(&mut qp, Read::Question(Current(0))).section, Some("Section A".to_string()));
+    }
+
+    #[test]
+    fn next_fresh_passes_over_touched_questions(){
+        let mut qp = paper(PAPER);
+        qp.resolve_write_intent(&Write::Mark(question(Start(1))));
+        qp.resolve_write_intent(&Write::Skip(question(Start(2))));
+        qp.resolve_write_intent(&Write::Answer(question(Start(4)), "evaporation".to_string()));
+        qp.restore_position(0);
+
+        assert_eq!(qp.next_fresh().unwrap().data.question_number(), Some(3));
+        assert_eq!(qp.next_fresh().unwrap().data.question_number(), Some(5));
+        assert_eq!(qp.next_fresh().unwrap_err(), "Nothing is left untouched");
+        assert_eq!(qp.prev_index(), 7);
+    }
+
+    #[test]
+    fn trace_keeps_the_latest_intents(){
+        let mut qp = paper(PAPER);
+        qp.resolve_intent(Intent::ReadIntent(Read::Question(Start(1))));
+        assert!(qp.trace().is_empty());
+
+        qp.set_trace_capacity(3);
+        qp.resolve_intent(Intent::ReadIntent(Read::Question(Start(1))));
+        qp.resolve_intent(Intent::ReadIntent(Read::Question(Start(9))));
+        qp.resolve_intent(Intent::WriteIntent(Write::Mark(question(Current(0)))));
+
+        let succeeded: Vec<bool> = qp.trace().iter().map(|entry| entry.succeeded).collect();
+        assert_eq!(succeeded, vec![true, false, true]);
+
+        // a fourth intent pushes out the oldest
+        qp.resolve_intent_ref(&Intent::Meta(MetaIntent::Marked));
+        assert_eq!(qp.trace().len(), 3);
+        assert_eq!(qp.trace()[0].intent, Intent::ReadIntent(Read::Question(Start(9))));
+        assert_eq!(qp.trace()[2].intent, Intent::Meta(MetaIntent::Marked));
+
+        qp.set_trace_capacity(1);
+        assert_eq!(qp.trace().len(), 1);
+        assert_eq!(qp.trace()[0].intent, Intent::Meta(MetaIntent::Marked));
+    }
+
+    #[test]
+    fn options_are_read_one_at_a_time(){
+        let mut qp = paper(PAPER);
+        assert_eq!(qp.next_option(), Err(Borrowed("Read a question before moving between its options")));
+
+        read(&mut qp, Read::Question(Start(1)));
+        assert_eq!(qp.previous_option(), Err(Borrowed("You are already at the first option")));
+        assert_eq!(qp.next_option(), Ok("Oxygen".to_string()));
+        assert_eq!(qp.next_option(), Ok("Carbon dioxide".to_string()));
+        assert_eq!(qp.next_option(), Ok("Nitrogen".to_string()));
+        assert_eq!(qp.next_option(), Err(Borrowed("There are no more options")));
+
+        assert_eq!(qp.previous_option(), Ok("Carbon dioxide".to_string()));
+        assert_eq!(qp.previous_option(), Ok("Oxygen".to_string()));
+        assert_eq!(qp.previous_option(), Err(Borrowed("You are already at the first option")));
+
+        // moving to another question starts its options afresh
+        qp.next_option().unwrap();
+        qp.next_option().unwrap();
+        read(&mut qp, Read::Question(Start(2)));
+        assert_eq!(qp.next_option(), Err(Borrowed("This question has no options")));
+
+        read(&mut qp, Read::Question(Start(1)));
+        assert_eq!(qp.next_option(), Ok("Oxygen".to_string()));
+    }
+
+    #[test]
+    fn transcript_layout_options(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_write_intent(&Write::Note(question(Start(1)), "revise gases".to_string()));
+        qp.resolve_write_intent(&Write::Note(question(Start(3)), "draw a diagram".to_string()));
+        qp.resolve_write_intent(&Write::GlobalNote("bring a ruler".to_string()));
+
+        let options = TranscriptOptions {
+            separator: " | ".to_string(),
+            header: Some("Your notes".to_string()),
+            ..TranscriptOptions::default()
+        };
+        assert_eq!(
+            qp.notes_transcript_with(&options),
+            "Your notes | Question 1: revise gases | Question 3: draw a diagram | General | - bring a ruler"
+        );
+
+        let options = TranscriptOptions {
+            order: NoteOrder::RecentFirst,
+            include_numbers: false,
+            ..TranscriptOptions::default()
+        };
+        assert_eq!(qp.notes_transcript_with(&options), "draw a diagram\nrevise gases\nGeneral\n- bring a ruler");
+
+        assert_eq!(
+            qp.notes_transcript_with(&TranscriptOptions::default()),
+            qp.notes_transcript(NoteOrder::default())
+        );
+    }
+
+    #[test]
+    fn orphan_notes_are_found(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_write_intent(&Write::Note(question(Start(1)), "revise gases".to_string()));
+        qp.resolve_write_intent(&Write::Note(question(Start(2)), "show working".to_string()));
+        qp.resolve_write_intent(&Write::GlobalNote("bring a ruler".to_string()));
+        assert!(qp.orphan_notes().is_empty());
+
+        // the second question is edited into a section header and a note is left on a missing node
+        let header = qp.nodes[1].data.clone();
+        qp.edit_nodes(|nodes| nodes[3].data = header);
+        qp.notes.push(Note { id: NodeId(99), note: "gone".to_string() });
+
+        let orphans: Vec<&str> = qp.orphan_notes().iter().map(|note| note.note.as_str()).collect();
+        assert_eq!(orphans, vec!["show working", "gone"]);
+    }
+
+    #[test]
+    fn marked_questions_with_their_notes(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_write_intent(&Write::MarkNumber(3));
+        qp.resolve_write_intent(&Write::MarkNumber(1));
+        qp.resolve_write_intent(&Write::Note(question(Start(1)), "revise gases".to_string()));
+        qp.resolve_write_intent(&Write::Note(question(Start(2)), "not marked".to_string()));
+
+        let review: Vec<(Option<u32>, Vec<&str>)> = qp.marked_with_notes().iter()
+            .map(|(data, notes)| (data.question_number(), notes.iter().map(|note| note.note.as_str()).collect()))
+            .collect();
+
+        assert_eq!(review, vec![(Some(1), vec!["revise gases"]), (Some(3), vec![])]);
+    }
+
+    #[test]
+    fn answering_can_advance_to_the_next_question(){
+        let mut qp = paper(PAPER);
+
+        let result = qp.resolve_write_intent(&Write::Answer(question(Start(1)), "Carbon dioxide".to_string()));
+        assert_eq!(result, WriteResult::Success("Your answer has been recorded".to_string()));
+        assert_eq!(qp.prev_index(), 0);
+
+        qp.set_advance_after_answer(true);
+
+        // the next question is in the following section
+        let result = qp.resolve_write_intent(&Write::AnswerText(question(Start(3)), "diffusion of water".to_string()));
+        assert_eq!(result, WriteResult::Advanced("Your answer has been recorded and requires manual grading".to_string(), qp.nodes[6].data.clone()));
+        assert_eq!(qp.prev_index(), 6);
+
+        let result = qp.resolve_write_intent(&Write::Answer(question(Start(5)), "protons and electrons".to_string()));
+        assert_eq!(result, WriteResult::Success("Your answer has been recorded, that was the last question".to_string()));
+        assert_eq!(qp.prev_index(), 6);
+        assert_eq!(qp.answer_of(qp.nodes[7].id).map(String::as_str), Some("protons and electrons"));
+    }
+
+    #[test]
+    fn sections_without_header_nodes(){
+        for inline in [true, false].iter() {
+            let mut builder = QPaperBuilder::new();
+            builder.inline_sections(*inline);
+
+            let mut qp = build(PAPER, builder);
+            let first = read(&mut qp, Read::Question(Start(1)));
+            assert_eq!(first.question_number(), Some(1));
+
+            qp.resolve_write_intent(&Write::Answer(question(Start(2)), "nucleus".to_string()));
+
+            assert_eq!(qp.remaining_in_section(), 2);
+            assert_eq!(qp.section_of(qp.prev_index()).map(|(_, name)| name), Some("Section A".to_string()));
+            assert_eq!(qp.section_progress(), vec![("Section A".to_string(), 1, 3), ("Section B".to_string(), 0, 2)]);
+            assert_eq!(qp.section_question_counts(), vec![("Section A".to_string(), 3), ("Section B".to_string(), 2)]);
+
+            let unanswered: Vec<Option<u32>> = qp.unanswered_in_section().iter().map(|data| data.question_number()).collect();
+            assert_eq!(unanswered, vec![Some(1), Some(3)]);
+
+            read(&mut qp, Read::Question(Start(5)));
+            assert_eq!(qp.remaining_in_section(), 0);
+            assert_eq!(qp.section_of(qp.prev_index()).map(|(_, name)| name), Some("Section B".to_string()));
+        }
+    }
+
+    #[test]
+    fn parts_past_z_get_double_letters(){
+        let label = |position| SubPartData::new(position, String::new(), 1).label;
+
+        assert_eq!(label(0), "a");
+        assert_eq!(label(25), "z");
+        assert_eq!(label(26), "aa");
+        assert_eq!(label(51), "az");
+        assert_eq!(label(52), "ba");
+        assert_eq!(label(200), "gs");
+        assert_eq!(label(701), "zz");
+        assert_eq!(label(702), "aaa");
+
+        let qp = QPaperBuilder::new().build_schema(PaperSchema {
+            sections: vec![SectionSchema {
+                name: "Section A".to_string(),
+                questions: vec![QuestionSchema {
+                    question: "Label the diagram.".to_string(),
+                    parts: (1..=30).map(|part| format!("Label {}", part)).collect(),
+                    ..QuestionSchema::default()
+                }]
+            }],
+            ..PaperSchema::default()
+        });
+
+        let labels: Vec<String> = qp.nodes.iter()
+            .filter_map(|node| match node.data {
+                NodeData::SubPart(ref part) => Some(part.label.clone()),
+                _ => None
+            })
+            .collect();
+        assert_eq!(labels.len(), 30);
+        assert_eq!(&labels[24..], ["y", "z", "aa", "ab", "ac", "ad"]);
+    }
+    #[test]
+    fn marked_questions_survive_node_edits(){
+        let mut qp = paper(PAPER);
+
+        for number in &[1, 3, 5] {
+            qp.resolve_write_intent(&Write::MarkNumber(*number));
+        }
+
+        // a question goes in ahead of the marks and the last section is cut off
+        let mut inserted = qp.nodes[2].clone();
+        inserted.id = NodeId(qp.len());
+        qp.edit_nodes(|nodes| {
+            nodes.insert(2, inserted);
+            nodes.truncate(6);
+        });
+
+        let expected = vec![qp.nodes[3].data.clone(), qp.nodes[5].data.clone()];
+        assert_eq!(qp.marked_page(0, 10), expected);
+        assert_eq!(qp.marked_with_notes().into_iter().map(|(data, _)| data).collect::<Vec<_>>(), expected);
+
+        assert!(qp.next_marked().is_ok());
+        assert_eq!(qp.prev_index(), 3);
+        assert!(qp.next_marked().is_ok());
+        assert_eq!(qp.prev_index(), 5);
+        assert!(qp.next_marked().is_err());
+    }
+    #[test]
+    fn time_spent_follows_the_node(){
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let mut qp = paper(PAPER);
+        let now = Arc::new(AtomicU64::new(0));
+        let clock = now.clone();
+        qp.set_clock(move || clock.load(Ordering::SeqCst));
+
+        read(&mut qp, Read::Question(Start(1)));
+        now.store(30, Ordering::SeqCst);
+        read(&mut qp, Read::Question(Start(5)));
+        now.store(40, Ordering::SeqCst);
+        read(&mut qp, Read::Question(Start(2)));
+
+        // a rollback takes back the time charged since the checkpoint
+        let checkpoint = qp.checkpoint();
+        now.store(100, Ordering::SeqCst);
+        read(&mut qp, Read::Question(Start(1)));
+        assert_eq!(qp.time_on(3), 60);
+
+        qp.rollback(checkpoint);
+        assert_eq!(qp.time_on(3), 0);
+        assert_eq!(qp.time_on(7), 10);
+
+        // a question goes in ahead of the first one and the last one is cut off
+        let mut inserted = qp.nodes[2].clone();
+        inserted.id = NodeId(qp.len());
+        qp.edit_nodes(|nodes| {
+            nodes.insert(2, inserted);
+            nodes.truncate(8);
+        });
+
+        assert_eq!(qp.time_on(2), 0);
+        assert_eq!(qp.time_on(3), 30);
+        assert_eq!(meta(&mut qp, MetaIntent::SlowestQuestions), "Most time went on question 1 (30 seconds)");
+
+        qp.prune_invalid();
+        assert_eq!(qp.time_spent.len(), 1);
+    }
+    #[test]
+    fn a_saved_session_restores_onto_a_fresh_paper(){
+        let mut qp = paper(PAPER);
+
+        qp.resolve_write_intent(&Write::Mark(question(Start(1))));
+        qp.resolve_write_intent(&Write::Mark(question(Start(4))));
+        qp.resolve_write_intent(&Write::Skip(question(Start(2))));
+        qp.resolve_write_intent(&Write::Note(question(Start(3)), "draw it & label".to_string()));
+        qp.resolve_write_intent(&Write::GlobalNote("bring a ruler".to_string()));
+        read(&mut qp, Read::Question(Start(3)));
+
+        let fragment = qp.session().to_fragment();
+
+        let mut resumed = paper(PAPER);
+        read(&mut resumed, Read::Question(Start(5)));
+        resumed.resolve_write_intent(&Write::Mark(question(Start(5))));
+
+        resumed.restore_session(&SessionState::from_fragment(&fragment).unwrap());
+
+        assert_eq!(resumed.session(), qp.session());
+        assert_eq!(resumed.prev_index(), 4);
+        assert_eq!(resumed.marked_index, qp.marked_index);
+        assert_eq!(resumed.skipped, qp.skipped);
+
+        // marked navigation runs over the restored marks
+        assert!(resumed.next_marked().is_ok());
+        assert_eq!(resumed.prev_index(), 6);
+        assert!(resumed.next_marked().is_err());
+
+        // marks and skips on nodes the paper does not have are dropped
+        let mut state = qp.session();
+        state.marked.insert(NodeId(99));
+        state.skipped.insert(NodeId(99));
+        resumed.restore_session(&state);
+        assert_eq!(resumed.session(), qp.session());
+    }
+}
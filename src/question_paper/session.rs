@@ -0,0 +1,225 @@
+use super::{NodeId, Note};
+use std::collections::BTreeSet;
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+/// A snapshot of what a user has done on a paper, independent of the paper itself
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct SessionState {
+    pub prev_index: usize,
+    pub marked: BTreeSet<NodeId>,
+    pub skipped: BTreeSet<NodeId>,
+    pub notes: Vec<Note>
+}
+
+/// Why a URL fragment could not be turned back into a session
+#[derive(Debug, Clone, PartialEq)]
+pub enum FragmentError {
+    /// The fragment is not url safe base64
+    Encoding,
+    /// The fragment decoded but does not describe a session
+    State(String)
+}
+
+impl fmt::Display for FragmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FragmentError::Encoding => write!(f, "The fragment is not valid base64"),
+            FragmentError::State(reason) => write!(f, "The fragment is not a saved session: {}", reason)
+        }
+    }
+}
+
+/// The changes between two session snapshots
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SessionDiff {
+    pub marks_added: BTreeSet<NodeId>,
+    pub marks_removed: BTreeSet<NodeId>,
+    pub skips_added: BTreeSet<NodeId>,
+    pub skips_removed: BTreeSet<NodeId>,
+    pub notes_added: Vec<Note>,
+    pub notes_removed: Vec<Note>
+}
+
+impl SessionState {
+    /// Report what changed going from this state to `other`
+    pub fn diff(&self, other: &SessionState) -> SessionDiff {
+        SessionDiff {
+            marks_added: other.marked.difference(&self.marked).cloned().collect(),
+            marks_removed: self.marked.difference(&other.marked).cloned().collect(),
+            skips_added: other.skipped.difference(&self.skipped).cloned().collect(),
+            skips_removed: self.skipped.difference(&other.skipped).cloned().collect(),
+            notes_added: missing_from(&other.notes, &self.notes),
+            notes_removed: missing_from(&self.notes, &other.notes)
+        }
+    }
+
+    /// Pool another session's marks, skips and notes into this one, keeping this cursor.
+    ///
+    /// Notes carry no timestamp, so neither side's notes win: both sets are kept, with the other
+    /// session's notes after this one's and any note both sessions already share kept once.
+    pub fn merge(&mut self, other: &SessionState) {
+        self.marked.extend(other.marked.iter().cloned());
+        self.skipped.extend(other.skipped.iter().cloned());
+
+        for incoming in &other.notes {
+            if !self.notes.contains(incoming) {
+                self.notes.push(incoming.clone());
+            }
+        }
+    }
+
+    /// Encode this state as url safe base64, for bookmarking a position
+    pub fn to_fragment(&self) -> String {
+        let json = serde_json::to_vec(self).expect("a session state always serializes");
+
+        base64::encode_config(&json, base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Decode a state written by `to_fragment`, a leading `#` is ignored
+    pub fn from_fragment(fragment: &str) -> Result<SessionState, FragmentError> {
+        let fragment = fragment.trim_start_matches('#');
+
+        let json = base64::decode_config(fragment, base64::URL_SAFE_NO_PAD)
+            .map_err(|_| FragmentError::Encoding)?;
+
+        serde_json::from_slice(&json).map_err(|e| FragmentError::State(e.to_string()))
+    }
+}
+
+impl SessionDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == SessionDiff::default()
+    }
+}
+
+// notes in `notes` that are not in `from`
+fn missing_from(notes: &[Note], from: &[Note]) -> Vec<Note> {
+    notes.iter()
+        .filter(|note| !from.contains(note))
+        .cloned()
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: usize, text: &str) -> Note {
+        Note {
+            id: NodeId(id),
+            note: text.to_string()
+        }
+    }
+
+    fn ids(ids: &[usize]) -> BTreeSet<NodeId> {
+        ids.iter().map(|id| NodeId(*id)).collect()
+    }
+
+    #[test]
+    fn diff_two_states(){
+        let before = SessionState {
+            prev_index: 2,
+            marked: ids(&[2, 3]),
+            skipped: ids(&[4]),
+            notes: vec![note(2, "revise"), note(3, "check units")]
+        };
+
+        let after = SessionState {
+            prev_index: 6,
+            marked: ids(&[3, 6]),
+            skipped: ids(&[4]),
+            notes: vec![note(3, "check units"), note(6, "draw it")]
+        };
+
+        let diff = before.diff(&after);
+
+        assert_eq!(diff.marks_added, ids(&[6]));
+        assert_eq!(diff.marks_removed, ids(&[2]));
+        assert!(diff.skips_added.is_empty());
+        assert!(diff.skips_removed.is_empty());
+        assert_eq!(diff.notes_added, vec![note(6, "draw it")]);
+        assert_eq!(diff.notes_removed, vec![note(2, "revise")]);
+    }
+
+    #[test]
+    fn diff_with_itself_is_empty(){
+        let state = SessionState {
+            marked: ids(&[2]),
+            notes: vec![note(2, "revise")],
+            ..SessionState::default()
+        };
+
+        assert!(state.diff(&state.clone()).is_empty());
+    }
+
+    #[test]
+    fn merge_overlapping_states(){
+        let mut mine = SessionState {
+            prev_index: 2,
+            marked: ids(&[2, 3]),
+            skipped: ids(&[4]),
+            notes: vec![note(2, "revise"), note(3, "check units")]
+        };
+
+        let theirs = SessionState {
+            prev_index: 7,
+            marked: ids(&[3, 6]),
+            skipped: ids(&[7]),
+            notes: vec![note(3, "check units"), note(3, "use SI units")]
+        };
+
+        mine.merge(&theirs);
+
+        assert_eq!(mine.prev_index, 2);
+        assert_eq!(mine.marked, ids(&[2, 3, 6]));
+        assert_eq!(mine.skipped, ids(&[4, 7]));
+        assert_eq!(mine.notes, vec![note(2, "revise"), note(3, "check units"), note(3, "use SI units")]);
+    }
+
+    #[test]
+    fn merge_keeps_general_notes_from_both(){
+        let general = |text: &str| Note { id: NodeId::GLOBAL, note: text.to_string() };
+
+        let mut mine = SessionState {
+            notes: vec![general("bring a ruler"), note(2, "revise"), note(2, "show working")],
+            ..SessionState::default()
+        };
+
+        let theirs = SessionState {
+            notes: vec![general("check the time"), note(2, "use a diagram")],
+            ..SessionState::default()
+        };
+
+        mine.merge(&theirs);
+
+        assert_eq!(mine.notes, vec![
+            general("bring a ruler"), note(2, "revise"), note(2, "show working"), general("check the time"), note(2, "use a diagram")
+        ]);
+    }
+
+    #[test]
+    fn round_trip_through_a_fragment(){
+        let state = SessionState {
+            prev_index: 6,
+            marked: ids(&[2, 6]),
+            skipped: ids(&[4]),
+            notes: vec![note(2, "revise"), note(6, "draw it & label")]
+        };
+
+        let fragment = state.to_fragment();
+        assert!(fragment.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        assert_eq!(SessionState::from_fragment(&fragment), Ok(state.clone()));
+        assert_eq!(SessionState::from_fragment(&format!("#{}", fragment)), Ok(state));
+    }
+
+    #[test]
+    fn reject_malformed_fragments(){
+        assert_eq!(SessionState::from_fragment("not base64!"), Err(FragmentError::Encoding));
+
+        let not_a_session = base64::encode_config(b"[1, 2, 3]", base64::URL_SAFE_NO_PAD);
+        assert!(matches!(SessionState::from_fragment(&not_a_session), Err(FragmentError::State(_))));
+    }
+}